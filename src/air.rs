@@ -0,0 +1,184 @@
+use std::fmt;
+
+use crate::multipolynomial::MPolynomial;
+
+/// Blowup above which a single constraint is considered to blow up the
+/// evaluation domain and gets a warning.
+const BLOWUP_WARNING_THRESHOLD: usize = 16;
+
+/// Degrees a set of transition constraints implies for the prover, for a trace
+/// of `trace_length` rows.
+///
+/// The transition constraints are polynomials in `2·w` variables, the `w`
+/// trace columns at the current row followed by the same columns at the next
+/// row, and are divided by the zerofier of every row but the last, of degree
+/// `trace_length - 1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DegreeAnalysis {
+    /// Largest total degree of the constraints in the trace variables, which
+    /// is the `air_degree` of the security estimate.
+    pub air_degree: usize,
+    /// Degree of every constraint once the trace polynomials are plugged in.
+    pub constraint_degrees: Vec<usize>,
+    /// Degree of every transition quotient.
+    pub quotient_degrees: Vec<usize>,
+    pub max_composition_degree: usize,
+    /// Smallest power of two with room for the composition polynomial, and at
+    /// least the trace length.
+    pub evaluation_domain_size: usize,
+    pub warnings: Vec<DegreeWarning>,
+}
+
+/// A constraint whose degree alone forces a large blowup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DegreeWarning {
+    pub constraint: usize,
+    pub degree: usize,
+    pub blowup: usize,
+}
+
+impl fmt::Display for DegreeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "transition constraint {} has degree {} and alone needs a blowup of {}, consider splitting it with an auxiliary column",
+            self.constraint, self.degree, self.blowup
+        )
+    }
+}
+
+impl DegreeAnalysis {
+    /// `column_degree_bounds[i]` bounds the degree of the polynomial
+    /// interpolating column `i`, usually `trace_length - 1` plus the number of
+    /// randomizers.
+    pub fn new(
+        trace_length: usize,
+        transition_constraints: &[MPolynomial],
+        column_degree_bounds: &[usize],
+    ) -> Self {
+        assert!(
+            trace_length.is_power_of_two(),
+            "trace length has to be a power of two"
+        );
+        let num_columns = column_degree_bounds.len();
+        assert!(
+            num_columns > 0,
+            "degree analysis needs the degree bound of at least one trace column"
+        );
+        assert!(
+            transition_constraints
+                .iter()
+                .all(|c| c.num_variables() <= 2 * num_columns),
+            "transition constraints can only refer to two rows of the trace"
+        );
+
+        let zerofier_degree = trace_length - 1;
+        let air_degree = transition_constraints
+            .iter()
+            .map(|c| c.total_degree().max(0) as usize)
+            .max()
+            .unwrap_or(0);
+        let constraint_degrees: Vec<usize> = transition_constraints
+            .iter()
+            .map(|c| symbolic_degree(c, column_degree_bounds))
+            .collect();
+        let quotient_degrees: Vec<usize> = constraint_degrees
+            .iter()
+            .map(|d| d.saturating_sub(zerofier_degree))
+            .collect();
+        let max_composition_degree = quotient_degrees.iter().copied().max().unwrap_or(0);
+
+        let warnings = quotient_degrees
+            .iter()
+            .enumerate()
+            .filter_map(|(i, d)| {
+                let blowup = domain_size(*d, trace_length) / trace_length;
+                (blowup > BLOWUP_WARNING_THRESHOLD).then_some(DegreeWarning {
+                    constraint: i,
+                    degree: constraint_degrees[i],
+                    blowup,
+                })
+            })
+            .collect();
+
+        DegreeAnalysis {
+            air_degree,
+            constraint_degrees,
+            quotient_degrees,
+            max_composition_degree,
+            evaluation_domain_size: domain_size(max_composition_degree, trace_length),
+            warnings,
+        }
+    }
+}
+
+/// Degree of `constraint` with the trace polynomials plugged in, where a
+/// variable of either row has the degree bound of its column.
+fn symbolic_degree(constraint: &MPolynomial, column_degree_bounds: &[usize]) -> usize {
+    let num_columns = column_degree_bounds.len();
+    constraint
+        .terms()
+        .map(|(exponents, _)| {
+            exponents
+                .iter()
+                .enumerate()
+                .map(|(i, e)| *e as usize * column_degree_bounds[i % num_columns])
+                .sum::<usize>()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn domain_size(degree: usize, trace_length: usize) -> usize {
+    (degree + 1).next_power_of_two().max(trace_length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algebra::Field;
+
+    /// `x_0^{e_0}·x_1^{e_1}·…` in four variables, two columns over two rows.
+    fn monomial(exponents: [u128; 4]) -> MPolynomial {
+        MPolynomial::new(vec![(exponents.to_vec(), Field::new().one())])
+    }
+
+    #[test]
+    fn degrees_follow_the_column_bounds() {
+        // x' - x·y and y' - y^2·x, with the columns of degree 7 and 9.
+        let constraints = [
+            monomial([0, 0, 1, 0]) - monomial([1, 1, 0, 0]),
+            monomial([0, 0, 0, 1]) - monomial([1, 2, 0, 0]),
+        ];
+        let analysis = DegreeAnalysis::new(8, &constraints, &[7, 9]);
+
+        assert_eq!(analysis.air_degree, 3);
+        assert_eq!(analysis.constraint_degrees, vec![16, 25]);
+        assert_eq!(analysis.quotient_degrees, vec![9, 18]);
+        assert_eq!(analysis.max_composition_degree, 18);
+        assert_eq!(analysis.evaluation_domain_size, 32);
+        assert!(analysis.warnings.is_empty());
+    }
+
+    #[test]
+    fn large_blowups_are_flagged() {
+        let constraints = [monomial([1, 0, 0, 0]), monomial([20, 0, 0, 0])];
+        let analysis = DegreeAnalysis::new(8, &constraints, &[7, 7]);
+
+        assert_eq!(analysis.evaluation_domain_size, 256);
+        assert_eq!(
+            analysis.warnings,
+            vec![DegreeWarning {
+                constraint: 1,
+                degree: 140,
+                blowup: 32,
+            }]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one trace column")]
+    fn no_columns_is_rejected() {
+        DegreeAnalysis::new(8, &[monomial([1, 0, 0, 0])], &[]);
+    }
+}
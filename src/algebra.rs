@@ -1,6 +1,9 @@
 use bigint::U256;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
 use std::error::Error as StdError;
+use std::fmt::Debug;
 use std::hash::Hash;
+use std::marker::PhantomData;
 use std::ops;
 
 /// Implementation from (https://stackoverflow.com/a/70501399)
@@ -41,65 +44,141 @@ pub fn xgcd(a: &U256, b: &U256) -> (U256, U256, U256) {
     (s0, t0, r0)
 }
 
-#[derive(Default, Copy, Clone, Debug)]
-pub struct FieldElement {
-    pub value: U256,
-    pub field: Field,
+/// Inverts every element with a single field inversion (Montgomery's trick).
+#[allow(dead_code)]
+pub fn batch_inverse<M: Modulus>(elements: &[Fp<M>]) -> Vec<Fp<M>> {
+    let field = match elements.first() {
+        Some(e) => e.field,
+        None => return Vec::new(),
+    };
+
+    let mut prefixes = Vec::with_capacity(elements.len());
+    let mut acc = field.one();
+    for e in elements {
+        assert!(!e.is_zero(), "can't invert zero");
+        prefixes.push(acc);
+        acc *= *e;
+    }
+
+    let mut inverse = acc.inverse();
+    let mut inverses = vec![field.zero(); elements.len()];
+    for ((inv, prefix), e) in inverses.iter_mut().zip(prefixes).zip(elements).rev() {
+        *inv = prefix * inverse;
+        inverse *= *e;
+    }
+
+    inverses
+}
+
+/// Prime modulus of a field, fixed at compile time so that elements of
+/// different fields are different types and don't have to carry their
+/// modulus around. `P` has to fit in 128 bits, so that the product of two
+/// elements fits in a `U256` before it's reduced. A larger modulus fails to
+/// build as soon as an element or handle of its field is constructed.
+pub trait Modulus:
+    Copy + Clone + Debug + Default + PartialEq + Eq + Hash + Send + Sync + 'static
+{
+    const P: U256;
+    /// Generator of the multiplicative group, which also generates the
+    /// subgroup of order `2^TWO_ADICITY` after squaring.
+    const GENERATOR: U256;
+    /// Largest `k` such that `2^k` divides `p - 1`.
+    const TWO_ADICITY: u32;
+}
+
+/// `407·2^119 + 1`, the field of the STARK.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Stark128;
+
+impl Modulus for Stark128 {
+    const P: U256 = U256([0x1, 0xcb80000000000000, 0, 0]);
+    const GENERATOR: U256 = U256([0xb5038f9c18f6f7d1, 0x4040fbed12ee470f, 0, 0]);
+    const TWO_ADICITY: u32 = 119;
+}
+
+pub type FieldElement = Fp<Stark128>;
+pub type Field = PrimeField<Stark128>;
+
+/// Element of the field with modulus `M`, always in `[0, p)`. The `field`
+/// handle is zero sized, so an element takes the 32 bytes of its value.
+///
+/// Deserializing goes through `from_canonical`, so a proof can't smuggle in
+/// an unreduced value.
+#[derive(Default, Copy, Clone, Debug, Serialize)]
+#[serde(bound = "")]
+pub struct Fp<M: Modulus> {
+    value: U256,
+    #[serde(skip)]
+    pub field: PrimeField<M>,
 }
 
-impl ops::Add for FieldElement {
-    type Output = FieldElement;
+impl<M: Modulus> ops::Add for Fp<M> {
+    type Output = Fp<M>;
 
     fn add(self, rhs: Self) -> Self::Output {
         self.field.add(&self, &rhs)
     }
 }
 
-impl ops::AddAssign for FieldElement {
+impl<M: Modulus> ops::AddAssign for Fp<M> {
     fn add_assign(&mut self, rhs: Self) {
         *self = *self + rhs;
     }
 }
 
-impl ops::Mul for FieldElement {
-    type Output = FieldElement;
+impl<M: Modulus> ops::Mul for Fp<M> {
+    type Output = Fp<M>;
 
     fn mul(self, rhs: Self) -> Self::Output {
         self.field.multiply(&self, &rhs)
     }
 }
 
-impl ops::MulAssign for FieldElement {
+impl<M: Modulus> ops::MulAssign for Fp<M> {
     fn mul_assign(&mut self, rhs: Self) {
         *self = *self * rhs;
     }
 }
 
-impl ops::Sub for FieldElement {
-    type Output = FieldElement;
+impl<M: Modulus> ops::Sub for Fp<M> {
+    type Output = Fp<M>;
 
     fn sub(self, rhs: Self) -> Self::Output {
         self.field.subtract(&self, &rhs)
     }
 }
 
-impl ops::Div for FieldElement {
-    type Output = FieldElement;
+impl<M: Modulus> ops::Div for Fp<M> {
+    type Output = Fp<M>;
 
     fn div(self, rhs: Self) -> Self::Output {
         self.field.divide(&self, &rhs)
     }
 }
 
-impl ops::Neg for FieldElement {
-    type Output = FieldElement;
+impl<M: Modulus> ops::Neg for Fp<M> {
+    type Output = Fp<M>;
 
     fn neg(self) -> Self::Output {
         self.field.negate(self)
     }
 }
 
-impl Hash for FieldElement {
+impl<'de, M: Modulus> Deserialize<'de> for Fp<M> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Same shape as the derived `Serialize`.
+        #[derive(Deserialize)]
+        #[serde(rename = "Fp")]
+        struct Repr {
+            value: U256,
+        }
+
+        let Repr { value } = Repr::deserialize(deserializer)?;
+        Self::from_canonical(value, PrimeField::new()).map_err(D::Error::custom)
+    }
+}
+
+impl<M: Modulus> Hash for Fp<M> {
     fn hash_slice<H: std::hash::Hasher>(data: &[Self], state: &mut H)
     where
         Self: Sized,
@@ -111,25 +190,68 @@ impl Hash for FieldElement {
     
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.value.hash(state);
-        self.field.hash(state);
     }
 }
 
-impl Eq for FieldElement {}
+impl<M: Modulus> Eq for Fp<M> {}
 
-impl PartialEq for FieldElement {
+impl<M: Modulus> PartialEq for Fp<M> {
     fn eq(&self, other: &Self) -> bool {
         self.value == other.value
     }
+}
 
-    fn ne(&self, other: &Self) -> bool {
-        !self.eq(other)
+impl<M: Modulus> Fp<M> {
+    /// Evaluated, and so checked at compile time, by every constructor of
+    /// `Fp<M>` and `PrimeField<M>`.
+    const MODULUS_FITS: () = assert!(
+        M::P.0[2] == 0 && M::P.0[3] == 0,
+        "modulus has to fit in 128 bits"
+    );
+
+    /// Panics unless `value` is already reduced, see `from_canonical`.
+    pub fn new(value: U256, field: PrimeField<M>) -> Self {
+        Self::from_canonical(value, field).unwrap()
+    }
+
+    /// The element with representative `value`, which has to be in `[0, p)`.
+    pub fn from_canonical(value: U256, field: PrimeField<M>) -> Result<Self, FieldError> {
+        let () = Self::MODULUS_FITS;
+        if value >= M::P {
+            return Err(FieldError {
+                message: format!("{} isn't reduced modulo {}", value, M::P),
+            });
+        }
+
+        Ok(Self { value, field })
+    }
+
+    /// The element `value mod p`, for any `value`.
+    pub fn from_reduced(value: U256, field: PrimeField<M>) -> Self {
+        let () = Self::MODULUS_FITS;
+        Self {
+            value: value % M::P,
+            field,
+        }
+    }
+
+    pub fn from_u64(value: u64, field: PrimeField<M>) -> Self {
+        Self::from_reduced(U256::from(value), field)
+    }
+
+    /// Negative values map to `p - |value|`.
+    pub fn from_i64(value: i64, field: PrimeField<M>) -> Self {
+        let magnitude = Self::from_u64(value.unsigned_abs(), field);
+        if value < 0 {
+            -magnitude
+        } else {
+            magnitude
+        }
     }
-}
 
-impl FieldElement {
-    pub fn new(value: U256, field: Field) -> Self {
-        Self { value, field }
+    /// The canonical representative, in `[0, p)`.
+    pub fn value(&self) -> U256 {
+        self.value
     }
 
     pub fn inverse(&self) -> Self {
@@ -137,15 +259,17 @@ impl FieldElement {
     }
 
     pub fn modexp(&self, exponent: usize) -> Self {
-        let one: U256 = U256::one();
-        let mut acc = FieldElement::new(one, self.field);
-        let val = FieldElement::new(self.value, self.field);
-
-        let binary_str = format!("{:b}", exponent);
-        for i in (0..binary_str.len() - 2).rev() {
-            acc = acc * acc;
-            if (1 << i) & exponent != 0 {
-                acc = acc * val;
+        self.modexp_u256(&U256::from(exponent as u64))
+    }
+
+    /// `modexp` for exponents of any size, such as the `1/α` power of a
+    /// Rescue round.
+    pub fn modexp_u256(&self, exponent: &U256) -> Self {
+        let mut acc = self.field.one();
+        for i in (0..exponent.bits()).rev() {
+            acc *= acc;
+            if exponent.bit(i) {
+                acc *= *self;
             }
         }
 
@@ -161,9 +285,17 @@ impl FieldElement {
     }
 }
 
-#[derive(Default, Clone, Copy, Debug)]
-pub struct Field {
-    p: U256,
+/// Handle to the field with modulus `M`. It holds no data; the modulus lives
+/// in the type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PrimeField<M: Modulus> {
+    modulus: PhantomData<M>,
+}
+
+impl<M: Modulus> Default for PrimeField<M> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Debug)]
@@ -179,134 +311,307 @@ impl std::fmt::Display for FieldError {
 
 impl StdError for FieldError {}
 
-impl Hash for Field {
-    fn hash_slice<H: std::hash::Hasher>(data: &[Self], state: &mut H)
-    where
-        Self: Sized,
-    {
-        for piece in data {
-            piece.hash(state)
+impl<M: Modulus> PrimeField<M> {
+    pub fn new() -> Self {
+        let () = Fp::<M>::MODULUS_FITS;
+        PrimeField {
+            modulus: PhantomData,
         }
     }
-    
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.p.hash(state);
+
+    pub fn modulus(&self) -> U256 {
+        M::P
     }
-}
 
-impl Field {
-    pub fn new(p: U256) -> Self {
-        Field { p }
+    /// Returns the bit length of the modulus.
+    pub fn bits(&self) -> usize {
+        M::P.bits()
     }
 
-    pub fn zero(&self) -> FieldElement {
-        FieldElement {
+    pub fn zero(&self) -> Fp<M> {
+        Fp {
             value: U256::zero(),
             field: *self,
         }
     }
 
-    pub fn one(&self) -> FieldElement {
-        FieldElement {
+    pub fn one(&self) -> Fp<M> {
+        Fp {
             value: U256::one(),
             field: *self,
         }
     }
 
-    pub fn add(&self, a: &FieldElement, b: &FieldElement) -> FieldElement {
-        FieldElement {
-            value: (a.value + b.value) % self.p,
+    pub fn add(&self, a: &Fp<M>, b: &Fp<M>) -> Fp<M> {
+        let sum = a.value + b.value;
+        Fp {
+            value: if sum >= M::P { sum - M::P } else { sum },
             field: *self,
         }
     }
 
-    pub fn multiply(&self, a: &FieldElement, b: &FieldElement) -> FieldElement {
-        FieldElement {
-            value: (a.value * b.value) % self.p,
+    pub fn multiply(&self, a: &Fp<M>, b: &Fp<M>) -> Fp<M> {
+        Fp {
+            value: (a.value * b.value) % M::P,
             field: *self,
         }
     }
 
-    pub fn subtract(&self, a: &FieldElement, b: &FieldElement) -> FieldElement {
-        FieldElement {
-            value: (a.value - b.value) % self.p,
+    pub fn subtract(&self, a: &Fp<M>, b: &Fp<M>) -> Fp<M> {
+        Fp {
+            value: if a.value >= b.value {
+                a.value - b.value
+            } else {
+                M::P - (b.value - a.value)
+            },
             field: *self,
         }
     }
 
-    pub fn divide(&self, a: &FieldElement, b: &FieldElement) -> FieldElement {
-        assert!(!b.is_zero());
-        let (s, _t, _r) = xgcd(&b.value, &self.p);
-
-        FieldElement {
-            value: (a.value * s) % self.p,
-            field: *self,
-        }
+    pub fn divide(&self, a: &Fp<M>, b: &Fp<M>) -> Fp<M> {
+        self.multiply(a, &self.inverse(*b))
     }
 
-    pub fn negate(&self, operand: FieldElement) -> FieldElement {
-        FieldElement {
-            value: (self.p - operand.value) % self.p,
+    pub fn negate(&self, operand: Fp<M>) -> Fp<M> {
+        Fp {
+            value: if operand.is_zero() {
+                operand.value
+            } else {
+                M::P - operand.value
+            },
             field: *self,
         }
     }
 
-    pub fn inverse(&self, operand: FieldElement) -> FieldElement {
-        let (a, _b, _g) = xgcd(&operand.value, &self.p);
+    pub fn inverse(&self, operand: Fp<M>) -> Fp<M> {
+        assert!(!operand.is_zero(), "can't invert zero");
+        let (a, _b, _g) = xgcd(&operand.value, &M::P);
 
-        FieldElement {
-            value: a,
+        Fp {
+            value: a % M::P,
             field: *self,
         }
     }
 
-    pub fn generator(&self) -> FieldElement {
-        let generator: U256 = U256::from_dec_str("85408008396924667383611388730472331217").unwrap();
-        let field_size: U256 =
-            U256::from_dec_str("270497897142230380135924736767050121217").unwrap();
-        assert!(self.p == field_size, "bro what field is that");
-        return FieldElement {
-            value: generator,
+    pub fn generator(&self) -> Fp<M> {
+        Fp {
+            value: M::GENERATOR,
             field: *self,
-        };
+        }
     }
 
     /// Ensures STARK property that the subgroup of power-of-two order exists by
     /// generating the "primitive nth root"
-    pub fn primite_nth_root(&self, n: &U256) -> Result<FieldElement, FieldError> {
+    pub fn primite_nth_root(&self, n: &U256) -> Result<Fp<M>, FieldError> {
         let zero: U256 = U256::zero();
         let one: U256 = U256::one();
-        let generator: U256 = U256::from_dec_str("85408008396924667383611388730472331217").unwrap();
-        let field_size: U256 =
-            U256::from_dec_str("270497897142230380135924736767050121217").unwrap();
-        if self.p == field_size {
-            assert!(
-                *n <= one << 119 && (*n & (*n - one)) == zero,
-                "field doesn't have the nth root of unity bro"
-            );
-            let mut root = FieldElement::new(generator, *self);
-            let mut order: U256 = one << 119;
-            while order != *n {
-                root = root.modexp(2);
-                order = order / U256::from(2);
+        let max_order: U256 = one << M::TWO_ADICITY as usize;
+        if *n == zero || *n > max_order || (*n & (*n - one)) != zero {
+            return Err(FieldError {
+                message: format!("field doesn't have a subgroup of order {} bro", n),
+            });
+        }
+
+        let mut root = self.generator();
+        let mut order = max_order;
+        while order != *n {
+            root = root.modexp(2);
+            order = order / U256::from(2);
+        }
+        Ok(root)
+    }
+
+    /// Turns random bytes into a field element, reading them as a big-endian
+    /// integer modulo p. The result is close to uniform once there are a good
+    /// number of bits more than the modulus has.
+    pub fn sample(&self, bytes: &[u8]) -> Fp<M> {
+        let base = U256::from(256);
+        let value = bytes
+            .iter()
+            .fold(U256::zero(), |acc, b| (acc * base + U256::from(*b)) % M::P);
+
+        Fp {
+            value,
+            field: *self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const ROUNDS: usize = 500;
+
+    fn is_canonical(e: &FieldElement) -> bool {
+        e.value() < Stark128::P
+    }
+
+    #[test]
+    fn construction_is_canonical() {
+        let field = Field::new();
+        let p = Stark128::P;
+
+        assert!(FieldElement::from_canonical(p, field).is_err());
+        assert!(FieldElement::from_canonical(U256::max_value(), field).is_err());
+        assert_eq!(
+            FieldElement::from_canonical(p - U256::one(), field).unwrap(),
+            -field.one()
+        );
+        assert_eq!(
+            FieldElement::from_reduced(p + U256::from(5), field),
+            FieldElement::from_u64(5, field)
+        );
+        assert_eq!(FieldElement::from_reduced(p, field), field.zero());
+        assert_eq!(FieldElement::from_i64(-1, field).value(), p - U256::one());
+        assert_eq!(FieldElement::from_i64(0, field), field.zero());
+        assert_eq!(
+            FieldElement::from_i64(i64::MIN, field) + FieldElement::from_u64(1 << 63, field),
+            field.zero()
+        );
+        assert_eq!(
+            FieldElement::from_i64(i64::MAX, field),
+            FieldElement::from_u64(i64::MAX as u64, field)
+        );
+    }
+
+    #[test]
+    fn deserialization_is_canonical() {
+        let field = Field::new();
+        let p = Stark128::P;
+
+        let a = FieldElement::from_u64(12345, field);
+        let bytes = bincode::serialize(&a).unwrap();
+        assert_eq!(bincode::deserialize::<FieldElement>(&bytes).unwrap(), a);
+
+        for value in [p, p + U256::one(), U256::max_value()] {
+            let bytes = bincode::serialize(&value).unwrap();
+            assert!(bincode::deserialize::<FieldElement>(&bytes).is_err());
+        }
+        let bytes = bincode::serialize(&(p - U256::one())).unwrap();
+        assert_eq!(
+            bincode::deserialize::<FieldElement>(&bytes).unwrap(),
+            -field.one()
+        );
+    }
+
+    #[test]
+    fn field_axioms() {
+        let field = Field::new();
+        let mut rng = testing::rng(0xf1e1d);
+        for _ in 0..ROUNDS {
+            let a = testing::element(&mut rng);
+            let b = testing::element(&mut rng);
+            let c = testing::element(&mut rng);
+
+            assert_eq!(a + b, b + a);
+            assert_eq!(a * b, b * a);
+            assert_eq!((a + b) + c, a + (b + c));
+            assert_eq!((a * b) * c, a * (b * c));
+            assert_eq!(a * (b + c), a * b + a * c);
+            assert_eq!(a + field.zero(), a);
+            assert_eq!(a * field.one(), a);
+            assert_eq!(a + -a, field.zero());
+            assert_eq!(a - b, a + -b);
+            assert_eq!(a - b + b, a);
+
+            for e in [a + b, a - b, a * b, -a] {
+                assert!(is_canonical(&e), "{} isn't reduced", e.bytes());
             }
-            Ok(root)
-        } else {
-            Err(FieldError { message: "u don't even know the field, how am i supposed to get the return root of unity???".to_string() })
         }
     }
 
-    /// Gets random bytes and turn them into a field element.
-    pub fn sample(&self, byte_array: &[U256]) -> FieldElement {
-        let zero: U256 = U256::zero();
-        let mut acc: U256 = zero;
-        for b in byte_array {
-            acc = (acc << 8) ^ *b;
+    #[test]
+    fn inverse_is_canonical() {
+        let field = Field::new();
+        let mut rng = testing::rng(0x1a7e);
+        for _ in 0..ROUNDS {
+            let a = testing::element(&mut rng);
+            let b = testing::element(&mut rng);
+            if a.is_zero() {
+                continue;
+            }
+
+            let inverse = a.inverse();
+            assert!(is_canonical(&inverse), "{} isn't reduced", inverse.bytes());
+            assert_eq!(a * inverse, field.one());
+            assert_eq!(b / a * a, b);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "can't invert zero")]
+    fn zero_has_no_inverse() {
+        Field::new().zero().inverse();
+    }
+
+    /// `base^exponent mod p` by square-and-multiply on plain `U256`s. Both
+    /// sides stay below `2^128`, so no product overflows.
+    fn naive_pow(base: U256, exponent: U256) -> U256 {
+        let p = Stark128::P;
+        let mut acc = U256::one();
+        for i in (0..exponent.bits()).rev() {
+            acc = acc * acc % p;
+            if exponent.bit(i) {
+                acc = acc * base % p;
+            }
         }
 
-        FieldElement {
-            value: acc % self.p,
-            field: *self,
+        acc
+    }
+
+    #[test]
+    fn arithmetic_matches_big_integers() {
+        let p = Stark128::P;
+        let mut rng = testing::rng(0xb16);
+        for _ in 0..ROUNDS {
+            let a = testing::element(&mut rng);
+            let b = testing::element(&mut rng);
+
+            assert_eq!((a + b).value(), (a.value() + b.value()) % p);
+            assert_eq!((a - b).value(), (a.value() + p - b.value()) % p);
+            assert_eq!((a * b).value(), a.value() * b.value() % p);
+            assert_eq!((-a).value(), (p - a.value()) % p);
+        }
+    }
+
+    #[test]
+    fn modexp_matches_big_integers() {
+        let field = Field::new();
+        let mut rng = testing::rng(0xe4);
+        for _ in 0..100 {
+            let a = testing::element(&mut rng);
+            let exponent = rng.next_u64() as usize;
+
+            assert_eq!(
+                a.modexp(exponent).value(),
+                naive_pow(a.value(), U256::from(exponent as u64))
+            );
+
+            let wide = U256([rng.next_u64(), rng.next_u64(), 0, 0]);
+            assert_eq!(a.modexp_u256(&wide).value(), naive_pow(a.value(), wide));
+        }
+
+        let a = rng.element();
+        let mut expected = field.one();
+        for exponent in 0..64 {
+            assert_eq!(a.modexp(exponent), expected);
+            expected *= a;
+        }
+    }
+
+    #[test]
+    fn inverse_matches_fermat() {
+        let p = Stark128::P;
+        let mut rng = testing::rng(0xfe4);
+        for _ in 0..100 {
+            let a = rng.element();
+            if a.is_zero() {
+                continue;
+            }
+
+            assert_eq!(a.inverse().value(), naive_pow(a.value(), p - U256::from(2)));
         }
     }
 }
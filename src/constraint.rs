@@ -0,0 +1,353 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::ops;
+
+use crate::{
+    algebra::{Field, FieldElement},
+    multipolynomial::MPolynomial,
+};
+
+/// A transition constraint written against named trace columns, e.g.
+/// `col("acc").next() - col("acc") * col("x")`.
+///
+/// Expressions are lowered to an `MPolynomial` in `2·w` variables for a trace
+/// of `w` columns: the current row first, then the next row, both in the order
+/// of the column names passed to `lower`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Constant(FieldElement),
+    Column { name: String, next: bool },
+    Neg(Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Sub(Box<Expression>, Box<Expression>),
+    Mul(Box<Expression>, Box<Expression>),
+}
+
+/// The value of column `name` in the current row.
+#[allow(dead_code)]
+pub fn col(name: &str) -> Expression {
+    Expression::Column {
+        name: name.to_string(),
+        next: false,
+    }
+}
+
+#[allow(dead_code)]
+pub fn constant(element: FieldElement) -> Expression {
+    Expression::Constant(element)
+}
+
+#[derive(Debug)]
+pub struct ConstraintError {
+    message: String,
+}
+
+impl fmt::Display for ConstraintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl StdError for ConstraintError {}
+
+impl Expression {
+    /// Moves every column of the expression to the next row. Transition
+    /// constraints only span two consecutive rows, so this can't be applied
+    /// twice.
+    pub fn next(self) -> Self {
+        match self {
+            Expression::Constant(c) => Expression::Constant(c),
+            Expression::Column { name, next } => {
+                assert!(!next, "column {} already refers to the next row", name);
+                Expression::Column { name, next: true }
+            }
+            Expression::Neg(e) => Expression::Neg(Box::new(e.next())),
+            Expression::Add(a, b) => Expression::Add(Box::new(a.next()), Box::new(b.next())),
+            Expression::Sub(a, b) => Expression::Sub(Box::new(a.next()), Box::new(b.next())),
+            Expression::Mul(a, b) => Expression::Mul(Box::new(a.next()), Box::new(b.next())),
+        }
+    }
+
+    /// Total degree in the trace columns. Terms that cancel out are still
+    /// counted, so this is an upper bound on the degree of the lowered
+    /// polynomial.
+    pub fn degree(&self) -> usize {
+        match self {
+            Expression::Constant(_) => 0,
+            Expression::Column { .. } => 1,
+            Expression::Neg(e) => e.degree(),
+            Expression::Add(a, b) | Expression::Sub(a, b) => a.degree().max(b.degree()),
+            Expression::Mul(a, b) => a.degree() + b.degree(),
+        }
+    }
+
+    /// Lowers the expression to a polynomial over the current and next rows
+    /// of a trace with the given `columns`.
+    pub fn lower(&self, columns: &[&str], field: &Field) -> Result<MPolynomial, ConstraintError> {
+        let variables = MPolynomial::variables(2 * columns.len(), field);
+
+        self.lower_with(columns, &variables)
+    }
+
+    /// Lowers the expression like `lower` for a trace that also has
+    /// `preprocessed` columns, committed once instead of by every proof, and
+    /// `periodic` columns, which the verifier evaluates itself. Each row lists
+    /// the trace columns first, then the preprocessed and the periodic ones.
+    pub fn lower_with_inputs(
+        &self,
+        trace: &[&str],
+        preprocessed: &[&str],
+        periodic: &[&str],
+        field: &Field,
+    ) -> Result<MPolynomial, ConstraintError> {
+        let columns: Vec<&str> = trace
+            .iter()
+            .chain(preprocessed)
+            .chain(periodic)
+            .copied()
+            .collect();
+        if let Some(name) = (0..columns.len())
+            .find_map(|i| columns[..i].contains(&columns[i]).then_some(columns[i]))
+        {
+            return Err(ConstraintError {
+                message: format!("column {} is defined twice", name),
+            });
+        }
+
+        self.lower(&columns, field)
+    }
+
+    fn lower_with(
+        &self,
+        columns: &[&str],
+        variables: &[MPolynomial],
+    ) -> Result<MPolynomial, ConstraintError> {
+        let polynomial = match self {
+            Expression::Constant(c) => MPolynomial::constant(variables.len(), *c),
+            Expression::Column { name, next } => {
+                let index =
+                    columns
+                        .iter()
+                        .position(|c| c == name)
+                        .ok_or_else(|| ConstraintError {
+                            message: format!("unknown trace column {}", name),
+                        })?;
+                let offset = if *next { columns.len() } else { 0 };
+                variables[offset + index].clone()
+            }
+            Expression::Neg(e) => -e.lower_with(columns, variables)?,
+            Expression::Add(a, b) => {
+                a.lower_with(columns, variables)? + b.lower_with(columns, variables)?
+            }
+            Expression::Sub(a, b) => {
+                a.lower_with(columns, variables)? - b.lower_with(columns, variables)?
+            }
+            Expression::Mul(a, b) => {
+                a.lower_with(columns, variables)? * b.lower_with(columns, variables)?
+            }
+        };
+
+        Ok(polynomial)
+    }
+}
+
+impl From<FieldElement> for Expression {
+    fn from(element: FieldElement) -> Self {
+        Expression::Constant(element)
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Constant(c) => write!(f, "{}", c.bytes()),
+            Expression::Column { name, next: false } => write!(f, "{}", name),
+            Expression::Column { name, next: true } => write!(f, "{}'", name),
+            Expression::Neg(e) => write!(f, "-({})", e),
+            Expression::Add(a, b) => write!(f, "({} + {})", a, b),
+            Expression::Sub(a, b) => write!(f, "({} - {})", a, b),
+            Expression::Mul(a, b) => write!(f, "{} * {}", a, b),
+        }
+    }
+}
+
+impl ops::Neg for Expression {
+    type Output = Expression;
+
+    fn neg(self) -> Self::Output {
+        Expression::Neg(Box::new(self))
+    }
+}
+
+impl ops::Neg for &Expression {
+    type Output = Expression;
+
+    fn neg(self) -> Self::Output {
+        -self.clone()
+    }
+}
+
+// Every binary operator for owned and borrowed operands alike, so that both
+// `col("a") * col("b")` and `&a * &b` read naturally.
+macro_rules! binary_operator {
+    ($trait:ident, $method:ident, $variant:ident) => {
+        impl ops::$trait for Expression {
+            type Output = Expression;
+
+            fn $method(self, rhs: Expression) -> Self::Output {
+                Expression::$variant(Box::new(self), Box::new(rhs))
+            }
+        }
+
+        impl ops::$trait<&Expression> for Expression {
+            type Output = Expression;
+
+            fn $method(self, rhs: &Expression) -> Self::Output {
+                Expression::$variant(Box::new(self), Box::new(rhs.clone()))
+            }
+        }
+
+        impl ops::$trait<Expression> for &Expression {
+            type Output = Expression;
+
+            fn $method(self, rhs: Expression) -> Self::Output {
+                Expression::$variant(Box::new(self.clone()), Box::new(rhs))
+            }
+        }
+
+        impl ops::$trait<&Expression> for &Expression {
+            type Output = Expression;
+
+            fn $method(self, rhs: &Expression) -> Self::Output {
+                Expression::$variant(Box::new(self.clone()), Box::new(rhs.clone()))
+            }
+        }
+
+        impl ops::$trait<FieldElement> for Expression {
+            type Output = Expression;
+
+            fn $method(self, rhs: FieldElement) -> Self::Output {
+                Expression::$variant(Box::new(self), Box::new(Expression::Constant(rhs)))
+            }
+        }
+
+        impl ops::$trait<FieldElement> for &Expression {
+            type Output = Expression;
+
+            fn $method(self, rhs: FieldElement) -> Self::Output {
+                Expression::$variant(Box::new(self.clone()), Box::new(Expression::Constant(rhs)))
+            }
+        }
+    };
+}
+
+binary_operator!(Add, add, Add);
+binary_operator!(Sub, sub, Sub);
+binary_operator!(Mul, mul, Mul);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{random::FieldRng, testing};
+
+    const COLUMNS: [&str; 3] = ["a", "b", "c"];
+
+    /// Random expression of at most `depth` nested operators.
+    fn random(rng: &mut FieldRng, depth: usize) -> Expression {
+        let choices = if depth == 0 { 2 } else { 6 };
+        match rng.below(choices) {
+            0 => constant(testing::element(rng)),
+            1 => {
+                let column = col(COLUMNS[rng.below(COLUMNS.len())]);
+                if rng.below(2) == 0 {
+                    column
+                } else {
+                    column.next()
+                }
+            }
+            2 => -random(rng, depth - 1),
+            3 => random(rng, depth - 1) + random(rng, depth - 1),
+            4 => random(rng, depth - 1) - random(rng, depth - 1),
+            _ => random(rng, depth - 1) * random(rng, depth - 1),
+        }
+    }
+
+    /// Walks the expression tree directly, without going through a polynomial.
+    fn evaluate(
+        expression: &Expression,
+        current: &[FieldElement],
+        next: &[FieldElement],
+    ) -> FieldElement {
+        match expression {
+            Expression::Constant(c) => *c,
+            Expression::Column {
+                name,
+                next: shifted,
+            } => {
+                let index = COLUMNS.iter().position(|c| c == name).unwrap();
+                if *shifted {
+                    next[index]
+                } else {
+                    current[index]
+                }
+            }
+            Expression::Neg(e) => -evaluate(e, current, next),
+            Expression::Add(a, b) => evaluate(a, current, next) + evaluate(b, current, next),
+            Expression::Sub(a, b) => evaluate(a, current, next) - evaluate(b, current, next),
+            Expression::Mul(a, b) => evaluate(a, current, next) * evaluate(b, current, next),
+        }
+    }
+
+    #[test]
+    fn lowering_matches_direct_evaluation() {
+        let field = Field::new();
+        let mut rng = testing::rng(36);
+        for _ in 0..200 {
+            let expression = random(&mut rng, 4);
+            let polynomial = expression.lower(&COLUMNS, &field).unwrap();
+            assert_eq!(polynomial.num_variables(), 2 * COLUMNS.len());
+            assert!(polynomial.total_degree() <= expression.degree() as i128);
+
+            let current: Vec<FieldElement> = (0..3).map(|_| testing::element(&mut rng)).collect();
+            let next: Vec<FieldElement> = (0..3).map(|_| testing::element(&mut rng)).collect();
+            let point = [current.as_slice(), next.as_slice()].concat();
+            assert_eq!(
+                polynomial.evaluate(&point),
+                evaluate(&expression, &current, &next),
+                "{}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_columns_are_an_error() {
+        let field = Field::new();
+        let expression = col("a") * col("d").next();
+
+        assert!(expression.lower(&COLUMNS, &field).is_err());
+        assert!(expression.lower(&["a", "d"], &field).is_ok());
+    }
+
+    #[test]
+    fn preprocessed_and_periodic_columns_follow_the_trace() {
+        let field = Field::new();
+        let expression = col("a").next() - col("a") * col("k") - col("p").next();
+
+        let polynomial = expression
+            .lower_with_inputs(&["a"], &["p"], &["k"], &field)
+            .unwrap();
+        assert_eq!(
+            polynomial,
+            expression.lower(&["a", "p", "k"], &field).unwrap()
+        );
+        assert!(expression.lower(&["a"], &field).is_err());
+
+        assert!(expression
+            .lower_with_inputs(&["a", "k"], &["p"], &["k"], &field)
+            .is_err());
+        assert!(expression
+            .lower_with_inputs(&["a"], &["p"], &["p", "k"], &field)
+            .is_err());
+    }
+}
@@ -0,0 +1,500 @@
+use crate::{
+    algebra::{batch_inverse, Field, FieldElement},
+    domain::Domain,
+    hash::Hasher,
+    multipolynomial::MPolynomial,
+    proof_stream::{ProofObject, ProofStream, SAMPLE_BYTES},
+    unipolynomial::UPolynomial,
+    vanishing::Vanishing,
+};
+
+/// Candidates drawn for the out-of-domain point before giving up. Each one
+/// lands in a domain with probability about `|domain| / p`.
+const OUT_OF_DOMAIN_ATTEMPTS: usize = 4;
+
+/// Evaluations of the trace polynomials at an out-of-domain point `z` and at
+/// its successor `ω·z` on the trace domain, and of the composition polynomial
+/// at `z` (DEEP-ALI).
+///
+/// The verifier recomputes the composition at `z` from the trace openings
+/// alone and compares it with the opened value, which links the trace to the
+/// AIR. FRI then only has to show that the DEEP quotients
+/// `(f(X) - f(z)) / (X - z)` are low degree, which needs far fewer queries
+/// than testing the trace codewords against the whole evaluation domain.
+///
+/// These are helpers for a DEEP-ALI prover and verifier, tested on their
+/// own. No STARK in the crate runs them yet: `MultiTableStark` checks its
+/// combined codeword against the tables at the queried rows instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeepOpenings {
+    pub point: FieldElement,
+    pub next_point: FieldElement,
+    pub current: Vec<FieldElement>,
+    pub next: Vec<FieldElement>,
+    pub composition: FieldElement,
+}
+
+#[allow(dead_code)]
+impl DeepOpenings {
+    /// Samples `z` from the transcript and sends the evaluations of every
+    /// trace polynomial at `z` and `ω·z` and of the composition polynomial at
+    /// `z`. The weights of the DEEP composition should be sampled after this,
+    /// so they bind the openings. Panics in the negligibly unlikely case that
+    /// every candidate for `z` lands in one of the domains.
+    pub fn prove<H: Hasher>(
+        proof_stream: &mut ProofStream<H>,
+        trace_polynomials: &[UPolynomial],
+        composition_polynomial: &UPolynomial,
+        trace_domain: &Domain,
+        evaluation_domain: &Domain,
+    ) -> Self {
+        let bytes = proof_stream.prover_fiat_shamir(SAMPLE_BYTES * OUT_OF_DOMAIN_ATTEMPTS);
+        let point = sample_out_of_domain(
+            &bytes,
+            trace_domain.field(),
+            &[*trace_domain, *evaluation_domain],
+        )
+        .expect("transcript keeps sampling points inside the domain");
+        let next_point = point * trace_domain.root();
+
+        let current: Vec<FieldElement> = trace_polynomials
+            .iter()
+            .map(|p| p.evaluate(&point))
+            .collect();
+        let next: Vec<FieldElement> = trace_polynomials
+            .iter()
+            .map(|p| p.evaluate(&next_point))
+            .collect();
+        let composition = composition_polynomial.evaluate(&point);
+        proof_stream.push(ProofObject::FieldElements(current.clone()));
+        proof_stream.push(ProofObject::FieldElements(next.clone()));
+        proof_stream.push(ProofObject::FieldElements(vec![composition]));
+
+        DeepOpenings {
+            point,
+            next_point,
+            current,
+            next,
+            composition,
+        }
+    }
+
+    /// Resamples `z` and reads the openings of `num_columns` trace polynomials
+    /// and of the composition polynomial back. `composition_at` recomputes the
+    /// composition from the trace openings as `(z, current, next)`, e.g. with
+    /// `transition_composition_at`. Returns `None` if no candidate for `z`
+    /// lies outside the domains, or if the proof doesn't contain the openings
+    /// or they don't satisfy the AIR at `z`.
+    pub fn verify<H: Hasher>(
+        proof_stream: &mut ProofStream<H>,
+        num_columns: usize,
+        trace_domain: &Domain,
+        evaluation_domain: &Domain,
+        composition_at: impl FnOnce(&FieldElement, &[FieldElement], &[FieldElement]) -> FieldElement,
+    ) -> Option<Self> {
+        let bytes = proof_stream.verifier_fiat_shamir(SAMPLE_BYTES * OUT_OF_DOMAIN_ATTEMPTS);
+        let point = sample_out_of_domain(
+            &bytes,
+            trace_domain.field(),
+            &[*trace_domain, *evaluation_domain],
+        )?;
+        let next_point = point * trace_domain.root();
+
+        let current = proof_stream.pull()?.into_field_elements()?;
+        let next = proof_stream.pull()?.into_field_elements()?;
+        let composition = match proof_stream.pull()?.into_field_elements()?[..] {
+            [composition] => composition,
+            _ => return None,
+        };
+        if current.len() != num_columns
+            || next.len() != num_columns
+            || composition_at(&point, &current, &next) != composition
+        {
+            return None;
+        }
+
+        Some(DeepOpenings {
+            point,
+            next_point,
+            current,
+            next,
+            composition,
+        })
+    }
+}
+
+/// The composition of transition quotients `Σ_k w_k·C_k(f(z), f(ω·z)) / Z(z)`
+/// from the trace values `current` at `z` and `next` at `ω·z`.
+#[allow(dead_code)]
+pub fn transition_composition_at(
+    constraints: &[MPolynomial],
+    weights: &[FieldElement],
+    zerofier: &Vanishing,
+    point: &FieldElement,
+    current: &[FieldElement],
+    next: &[FieldElement],
+) -> FieldElement {
+    assert!(
+        weights.len() == constraints.len(),
+        "need one weight per constraint"
+    );
+    let row = [current, next].concat();
+    let combined = constraints
+        .iter()
+        .zip(weights)
+        .fold(point.field.zero(), |acc, (c, w)| {
+            acc + *w * c.evaluate(&row)
+        });
+
+    combined / zerofier.evaluate(point)
+}
+
+/// Returns `(f(X) - f(z)) / (X - z)` over `domain`, given the codeword of `f`
+/// and its claimed value at `z`.
+#[allow(dead_code)]
+pub fn deep_quotient(
+    codeword: &[FieldElement],
+    domain: &[FieldElement],
+    point: &FieldElement,
+    value: &FieldElement,
+) -> Vec<FieldElement> {
+    assert!(
+        codeword.len() == domain.len(),
+        "codeword and domain not the same length"
+    );
+    let differences: Vec<FieldElement> = domain.iter().map(|x| *x - *point).collect();
+
+    codeword
+        .iter()
+        .zip(batch_inverse(&differences))
+        .map(|(c, inv)| (*c - *value) * inv)
+        .collect()
+}
+
+/// Random linear combination of the DEEP quotients of every trace codeword at
+/// `z` and `ω·z` and of the composition codeword at `z`, which is the codeword
+/// FRI runs on. `weights` holds two weights per column, the one for `z`
+/// first, and then the weight of the composition.
+#[allow(dead_code)]
+pub fn deep_composition(
+    trace_codewords: &[Vec<FieldElement>],
+    composition_codeword: &[FieldElement],
+    domain: &Domain,
+    openings: &DeepOpenings,
+    weights: &[FieldElement],
+) -> Vec<FieldElement> {
+    assert!(
+        weights.len() == 2 * trace_codewords.len() + 1,
+        "need two weights per trace column and one for the composition"
+    );
+    assert!(
+        composition_codeword.len() == domain.size(),
+        "codeword and domain not the same length"
+    );
+    assert!(
+        trace_codewords.len() == openings.current.len(),
+        "every trace column needs an opening"
+    );
+
+    let elements = domain.elements();
+    let current_inverses = batch_inverse(
+        &elements
+            .iter()
+            .map(|x| *x - openings.point)
+            .collect::<Vec<FieldElement>>(),
+    );
+    let next_inverses = batch_inverse(
+        &elements
+            .iter()
+            .map(|x| *x - openings.next_point)
+            .collect::<Vec<FieldElement>>(),
+    );
+
+    let composition_weight = weights[weights.len() - 1];
+    let mut acc: Vec<FieldElement> = composition_codeword
+        .iter()
+        .zip(&current_inverses)
+        .map(|(h, inv)| composition_weight * (*h - openings.composition) * *inv)
+        .collect();
+    for (j, codeword) in trace_codewords.iter().enumerate() {
+        assert!(
+            codeword.len() == domain.size(),
+            "codeword and domain not the same length"
+        );
+        for (i, c) in codeword.iter().enumerate() {
+            acc[i] += weights[2 * j] * (*c - openings.current[j]) * current_inverses[i];
+            acc[i] += weights[2 * j + 1] * (*c - openings.next[j]) * next_inverses[i];
+        }
+    }
+
+    acc
+}
+
+/// The DEEP composition at a single point `x` from the trace and composition
+/// values opened there, which the verifier compares against the first FRI
+/// codeword at every query.
+#[allow(dead_code)]
+pub fn deep_composition_at(
+    x: &FieldElement,
+    trace_values: &[FieldElement],
+    composition_value: &FieldElement,
+    openings: &DeepOpenings,
+    weights: &[FieldElement],
+) -> FieldElement {
+    let current_inverse = (*x - openings.point).inverse();
+    let next_inverse = (*x - openings.next_point).inverse();
+    let composition_weight = weights[weights.len() - 1];
+    let composition =
+        composition_weight * (*composition_value - openings.composition) * current_inverse;
+
+    trace_values
+        .iter()
+        .enumerate()
+        .fold(composition, |acc, (j, t)| {
+            acc + weights[2 * j] * (*t - openings.current[j]) * current_inverse
+                + weights[2 * j + 1] * (*t - openings.next[j]) * next_inverse
+        })
+}
+
+/// Picks the first candidate that lies outside every one of `domains`, or
+/// `None` if there is none.
+fn sample_out_of_domain(bytes: &[u8], field: Field, domains: &[Domain]) -> Option<FieldElement> {
+    bytes
+        .chunks(SAMPLE_BYTES)
+        .map(|chunk| field.sample(chunk))
+        .find(|z| {
+            domains
+                .iter()
+                .all(|d| z.modexp(d.size()) != d.offset().modexp(d.size()))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constraint::col, evaluation::EvaluationForm, hash::Sha3, testing};
+
+    const TRACE_LENGTH: usize = 8;
+
+    /// `a' = b` and `b' = a·b` over a trace of eight rows, with everything
+    /// the prover and the verifier derive from it.
+    struct Fixture {
+        trace_domain: Domain,
+        evaluation_domain: Domain,
+        constraints: Vec<MPolynomial>,
+        weights: Vec<FieldElement>,
+        zerofier: Vanishing,
+        trace_polynomials: Vec<UPolynomial>,
+        composition_polynomial: UPolynomial,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let field = Field::new();
+            let mut rng = testing::rng(30);
+            let trace_domain = Domain::new(TRACE_LENGTH, field).unwrap();
+            let evaluation_domain = Domain::coset(4 * TRACE_LENGTH, field.generator()).unwrap();
+
+            let mut a = vec![FieldElement::from_u64(2, field)];
+            let mut b = vec![FieldElement::from_u64(3, field)];
+            for i in 0..TRACE_LENGTH - 1 {
+                a.push(b[i]);
+                b.push(a[i] * b[i]);
+            }
+            let columns = ["a", "b"];
+            let constraints = [
+                col("a").next() - col("b"),
+                col("b").next() - col("a") * col("b"),
+            ]
+            .iter()
+            .map(|c| c.lower(&columns, &field).unwrap())
+            .collect();
+            let trace_polynomials: Vec<UPolynomial> = [a, b]
+                .into_iter()
+                .map(|column| EvaluationForm::new(trace_domain, column).to_polynomial())
+                .collect();
+
+            let mut fixture = Fixture {
+                trace_domain,
+                evaluation_domain,
+                constraints,
+                weights: rng.elements(2),
+                zerofier: Vanishing::transition(&trace_domain.root(), TRACE_LENGTH),
+                trace_polynomials,
+                composition_polynomial: UPolynomial::new(Vec::new()),
+            };
+            let composition: Vec<FieldElement> = evaluation_domain
+                .iter()
+                .map(|x| fixture.composition_at(&x))
+                .collect();
+            fixture.composition_polynomial =
+                EvaluationForm::new(evaluation_domain, composition).to_polynomial();
+
+            fixture
+        }
+
+        fn composition_at(&self, x: &FieldElement) -> FieldElement {
+            let next_x = *x * self.trace_domain.root();
+            let current: Vec<FieldElement> = self
+                .trace_polynomials
+                .iter()
+                .map(|p| p.evaluate(x))
+                .collect();
+            let next: Vec<FieldElement> = self
+                .trace_polynomials
+                .iter()
+                .map(|p| p.evaluate(&next_x))
+                .collect();
+            self.check(x, &current, &next)
+        }
+
+        fn check(
+            &self,
+            x: &FieldElement,
+            current: &[FieldElement],
+            next: &[FieldElement],
+        ) -> FieldElement {
+            transition_composition_at(
+                &self.constraints,
+                &self.weights,
+                &self.zerofier,
+                x,
+                current,
+                next,
+            )
+        }
+
+        fn prove(&self) -> (ProofStream<Sha3>, DeepOpenings) {
+            let mut proof_stream = ProofStream::new();
+            let openings = DeepOpenings::prove(
+                &mut proof_stream,
+                &self.trace_polynomials,
+                &self.composition_polynomial,
+                &self.trace_domain,
+                &self.evaluation_domain,
+            );
+
+            (proof_stream, openings)
+        }
+
+        fn verify(&self, proof_stream: &mut ProofStream<Sha3>) -> Option<DeepOpenings> {
+            DeepOpenings::verify(
+                proof_stream,
+                2,
+                &self.trace_domain,
+                &self.evaluation_domain,
+                |x, current, next| self.check(x, current, next),
+            )
+        }
+    }
+
+    #[test]
+    fn honest_openings_verify() {
+        let fixture = Fixture::new();
+        // The constraints hold on the trace, so the composition is a
+        // polynomial of degree 2·(n - 1) - (n - 1).
+        assert_eq!(
+            fixture.composition_polynomial.degree(),
+            TRACE_LENGTH as i128 - 1
+        );
+
+        let (mut proof_stream, openings) = fixture.prove();
+        assert_eq!(fixture.verify(&mut proof_stream), Some(openings));
+    }
+
+    #[test]
+    fn out_of_domain_sampling_can_fail() {
+        let fixture = Fixture::new();
+        let field = Field::new();
+        let domains = [fixture.trace_domain, fixture.evaluation_domain];
+
+        // Every candidate reads as 1, which is in the trace domain.
+        let mut inside = vec![0; SAMPLE_BYTES * OUT_OF_DOMAIN_ATTEMPTS];
+        for chunk in inside.chunks_mut(SAMPLE_BYTES) {
+            chunk[SAMPLE_BYTES - 1] = 1;
+        }
+        assert_eq!(sample_out_of_domain(&inside, field, &domains), None);
+
+        let mut outside = inside.clone();
+        outside[2 * SAMPLE_BYTES - 1] = 2;
+        assert_eq!(
+            sample_out_of_domain(&outside, field, &domains),
+            Some(FieldElement::from_u64(2, field))
+        );
+    }
+
+    #[test]
+    fn wrong_openings_are_rejected() {
+        let fixture = Fixture::new();
+        let (_, openings) = fixture.prove();
+        let field = Field::new();
+
+        let tamper = |current: Vec<FieldElement>, next: Vec<FieldElement>, composition| {
+            let mut proof_stream = ProofStream::new();
+            proof_stream.push(ProofObject::FieldElements(current));
+            proof_stream.push(ProofObject::FieldElements(next));
+            proof_stream.push(ProofObject::FieldElements(vec![composition]));
+            fixture.verify(&mut proof_stream)
+        };
+        let (current, next) = (openings.current.clone(), openings.next.clone());
+
+        assert!(tamper(current.clone(), next.clone(), openings.composition).is_some());
+        assert!(tamper(
+            current.clone(),
+            next.clone(),
+            openings.composition + field.one()
+        )
+        .is_none());
+        let mut wrong_trace = current.clone();
+        wrong_trace[1] += field.one();
+        assert!(tamper(wrong_trace, next, openings.composition).is_none());
+    }
+
+    #[test]
+    fn deep_composition_is_low_degree_only_for_true_openings() {
+        let fixture = Fixture::new();
+        let (_, openings) = fixture.prove();
+        let mut rng = testing::rng(31);
+        let weights = rng.elements(5);
+        let domain = fixture.evaluation_domain;
+        let elements = domain.elements();
+        let trace_codewords: Vec<Vec<FieldElement>> = fixture
+            .trace_polynomials
+            .iter()
+            .map(|p| p.evaluate_domain(&elements))
+            .collect();
+        let composition_codeword = fixture.composition_polynomial.evaluate_domain(&elements);
+        let degree = |openings: &DeepOpenings| {
+            let codeword = deep_composition(
+                &trace_codewords,
+                &composition_codeword,
+                &domain,
+                openings,
+                &weights,
+            );
+            let i = 5;
+            let trace_values: Vec<FieldElement> = trace_codewords.iter().map(|c| c[i]).collect();
+            assert_eq!(
+                deep_composition_at(
+                    &elements[i],
+                    &trace_values,
+                    &composition_codeword[i],
+                    openings,
+                    &weights
+                ),
+                codeword[i]
+            );
+
+            EvaluationForm::new(domain, codeword)
+                .to_polynomial()
+                .degree()
+        };
+
+        assert!(degree(&openings) < TRACE_LENGTH as i128 - 1);
+        let wrong = DeepOpenings {
+            composition: openings.composition + Field::new().one(),
+            ..openings
+        };
+        assert!(degree(&wrong) >= TRACE_LENGTH as i128);
+    }
+}
@@ -0,0 +1,73 @@
+use bigint::U256;
+
+use crate::{
+    algebra::{Field, FieldElement, FieldError},
+    vanishing::Vanishing,
+};
+
+/// Multiplicative coset `offset·⟨ω⟩` where `ω` generates the subgroup of
+/// power-of-two order `size`. With `offset = 1` this is the subgroup itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Domain {
+    offset: FieldElement,
+    root: FieldElement,
+    size: usize,
+}
+
+impl Domain {
+    pub fn new(size: usize, field: Field) -> Result<Self, FieldError> {
+        Domain::coset(size, field.one())
+    }
+
+    pub fn coset(size: usize, offset: FieldElement) -> Result<Self, FieldError> {
+        assert!(
+            size.is_power_of_two(),
+            "domain size has to be a power of two"
+        );
+        assert!(!offset.is_zero(), "domain offset can't be zero");
+        let root = offset.field.primite_nth_root(&U256::from(size as u64))?;
+
+        Ok(Domain { offset, root, size })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn offset(&self) -> FieldElement {
+        self.offset
+    }
+
+    /// Returns the generator `ω` of the underlying subgroup.
+    pub fn root(&self) -> FieldElement {
+        self.root
+    }
+
+    pub fn field(&self) -> Field {
+        self.offset.field
+    }
+
+    pub fn element(&self, index: usize) -> FieldElement {
+        self.offset * self.root.modexp(index % self.size)
+    }
+
+    pub fn elements(&self) -> Vec<FieldElement> {
+        self.iter().collect()
+    }
+
+    /// The elements in order, computed one multiplication at a time.
+    pub fn iter(&self) -> impl Iterator<Item = FieldElement> {
+        let root = self.root;
+        let mut acc = self.offset;
+        (0..self.size).map(move |_| {
+            let element = acc;
+            acc *= root;
+            element
+        })
+    }
+
+    /// Returns the polynomial vanishing on the whole domain.
+    pub fn vanishing(&self) -> Vanishing {
+        Vanishing::coset(self.size, self.offset)
+    }
+}
@@ -0,0 +1,189 @@
+use std::ops;
+
+use crate::{
+    algebra::{batch_inverse, FieldElement},
+    domain::Domain,
+    ntt::{intt, ntt},
+    unipolynomial::UPolynomial,
+};
+
+/// Polynomial of degree less than `domain.size()` held as its values over
+/// `domain`, which is how the prover mostly sees its polynomials.
+///
+/// The arithmetic is pointwise, so a product is only meaningful while its
+/// degree stays below the domain size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvaluationForm {
+    domain: Domain,
+    values: Vec<FieldElement>,
+}
+
+impl ops::Neg for EvaluationForm {
+    type Output = EvaluationForm;
+
+    fn neg(self) -> Self::Output {
+        EvaluationForm {
+            domain: self.domain,
+            values: self.values.into_iter().map(|v| -v).collect(),
+        }
+    }
+}
+
+impl ops::Add for EvaluationForm {
+    type Output = EvaluationForm;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.pointwise(&rhs, |a, b| a + b)
+    }
+}
+
+impl ops::Sub for EvaluationForm {
+    type Output = EvaluationForm;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.pointwise(&rhs, |a, b| a - b)
+    }
+}
+
+impl ops::Mul for EvaluationForm {
+    type Output = EvaluationForm;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.pointwise(&rhs, |a, b| a * b)
+    }
+}
+
+impl EvaluationForm {
+    pub fn new(domain: Domain, values: Vec<FieldElement>) -> Self {
+        assert!(
+            values.len() == domain.size(),
+            "domain and values not the same length"
+        );
+        EvaluationForm { domain, values }
+    }
+
+    /// Evaluates `polynomial` over `domain` with an NTT. Coefficients past the
+    /// domain size are folded back first, using `x^n = offset^n` on the coset.
+    pub fn from_polynomial(polynomial: &UPolynomial, domain: Domain) -> Self {
+        let field = domain.field();
+        let n = domain.size();
+        let shift = domain.offset().modexp(n);
+
+        let mut folded = vec![field.zero(); n];
+        let mut factor = field.one();
+        for chunk in polynomial.coefficients().chunks(n) {
+            for (f, c) in folded.iter_mut().zip(chunk) {
+                *f += factor * *c;
+            }
+            factor *= shift;
+        }
+
+        let mut power = field.one();
+        for f in folded.iter_mut() {
+            *f *= power;
+            power *= domain.offset();
+        }
+
+        EvaluationForm {
+            domain,
+            values: ntt(&folded, &domain.root()),
+        }
+    }
+
+    /// Interpolates the coefficients with an inverse NTT.
+    pub fn to_polynomial(&self) -> UPolynomial {
+        let field = self.domain.field();
+        let offset_inverse = self.domain.offset().inverse();
+
+        let mut power = field.one();
+        let coefficients = intt(&self.values, &self.domain.root())
+            .into_iter()
+            .map(|c| {
+                let coefficient = c * power;
+                power *= offset_inverse;
+                coefficient
+            })
+            .collect();
+
+        UPolynomial::new(coefficients)
+    }
+
+    /// Evaluates at an arbitrary point in `O(n)` with the barycentric formula
+    /// for a coset `c·⟨ω⟩` of order `n`:
+    ///
+    /// `f(z) = (z^n - c^n) / (n·c^n) · Σ f_i·x_i / (z - x_i)`
+    #[allow(dead_code)]
+    pub fn evaluate(&self, point: &FieldElement) -> FieldElement {
+        let field = self.domain.field();
+        let differences: Vec<FieldElement> = self.domain.iter().map(|x| *point - x).collect();
+        if let Some(i) = differences.iter().position(|d| d.is_zero()) {
+            return self.values[i];
+        }
+
+        let sum = self
+            .values
+            .iter()
+            .zip(self.domain.iter())
+            .zip(batch_inverse(&differences))
+            .fold(field.zero(), |acc, ((f, x), inv)| acc + *f * x * inv);
+
+        let n = self.domain.size();
+        let shift = self.domain.offset().modexp(n);
+        let size = FieldElement::from_u64(n as u64, field);
+
+        (point.modexp(n) - shift) / (size * shift) * sum
+    }
+
+    #[allow(dead_code)]
+    pub fn domain(&self) -> &Domain {
+        &self.domain
+    }
+
+    pub fn values(&self) -> &[FieldElement] {
+        &self.values
+    }
+
+    fn pointwise(
+        &self,
+        rhs: &Self,
+        op: impl Fn(FieldElement, FieldElement) -> FieldElement,
+    ) -> Self {
+        assert!(
+            self.domain == rhs.domain,
+            "can't combine evaluations over different domains"
+        );
+        EvaluationForm {
+            domain: self.domain,
+            values: self
+                .values
+                .iter()
+                .zip(&rhs.values)
+                .map(|(a, b)| op(*a, *b))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{algebra::Field, testing};
+
+    #[test]
+    fn barycentric_evaluation_matches_the_interpolant() {
+        let field = Field::new();
+        let mut rng = testing::rng(29);
+        for size in [1, 2, 8, 64] {
+            let domains = [Domain::new(size, field).unwrap(), rng.coset(size).unwrap()];
+            for domain in domains {
+                let polynomial = rng.polynomial(size - 1);
+                let form = EvaluationForm::from_polynomial(&polynomial, domain);
+                assert_eq!(form.to_polynomial(), polynomial);
+
+                for point in domain.iter().chain(rng.elements(8)) {
+                    assert_eq!(form.evaluate(&point), polynomial.evaluate(&point));
+                }
+            }
+        }
+    }
+}
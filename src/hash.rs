@@ -0,0 +1,338 @@
+use std::sync::OnceLock;
+
+use bigint::U256;
+use blake2::Blake2s256;
+use serde::{Deserialize, Serialize};
+use sha3::{
+    digest::{Digest as _, ExtendableOutput, Update, XofReader},
+    Keccak256, Sha3_256, Shake256,
+};
+
+use crate::algebra::{Field, FieldElement};
+
+pub type Digest = [u8; 32];
+
+/// Identifies a hash backend in proof headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashId {
+    Sha3,
+    Keccak,
+    Blake2s,
+    Blake3,
+    RescuePrime,
+}
+
+/// Hash function behind Merkle commitments and Fiat-Shamir challenges.
+///
+/// Byte-oriented hashes are the fast choice for native verification, while
+/// `RescuePrime` works over `Field` and is the one to pick for proofs that get
+/// verified inside another STARK.
+pub trait Hasher {
+    const ID: HashId;
+
+    fn hash(data: &[u8]) -> Digest;
+
+    /// Stretches `data` into `num_bytes` pseudorandom bytes, by default by
+    /// hashing it with a running block counter.
+    fn expand(data: &[u8], num_bytes: usize) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(num_bytes);
+        let mut counter: u64 = 0;
+        while bytes.len() < num_bytes {
+            bytes.extend_from_slice(&Self::hash(&[data, &counter.to_le_bytes()].concat()));
+            counter += 1;
+        }
+        bytes.truncate(num_bytes);
+
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[allow(dead_code)]
+pub struct Sha3;
+
+impl Hasher for Sha3 {
+    const ID: HashId = HashId::Sha3;
+
+    fn hash(data: &[u8]) -> Digest {
+        Sha3_256::digest(data).into()
+    }
+
+    fn expand(data: &[u8], num_bytes: usize) -> Vec<u8> {
+        let mut hasher = Shake256::default();
+        hasher.update(data);
+
+        let mut bytes = vec![0; num_bytes];
+        hasher.finalize_xof().read(&mut bytes);
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[allow(dead_code)]
+pub struct Keccak;
+
+impl Hasher for Keccak {
+    const ID: HashId = HashId::Keccak;
+
+    fn hash(data: &[u8]) -> Digest {
+        Keccak256::digest(data).into()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[allow(dead_code)]
+pub struct Blake2s;
+
+impl Hasher for Blake2s {
+    const ID: HashId = HashId::Blake2s;
+
+    fn hash(data: &[u8]) -> Digest {
+        Blake2s256::digest(data).into()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[allow(dead_code)]
+pub struct Blake3;
+
+impl Hasher for Blake3 {
+    const ID: HashId = HashId::Blake3;
+
+    fn hash(data: &[u8]) -> Digest {
+        blake3::hash(data).into()
+    }
+
+    fn expand(data: &[u8], num_bytes: usize) -> Vec<u8> {
+        let mut bytes = vec![0; num_bytes];
+        blake3::Hasher::new()
+            .update(data)
+            .finalize_xof()
+            .fill(&mut bytes);
+        bytes
+    }
+}
+
+/// Rescue-Prime over the STARK field with a state of three elements, two of
+/// them capacity, `α = 3` and 18 rounds. Bytes are absorbed in chunks of 15
+/// behind a marker byte, so every chunk fits below the modulus and chunks of
+/// different lengths never map to the same element. A digest is two squeezed
+/// elements of 16 bytes.
+///
+/// The capacity sets the generic security of the sponge: `c` elements of a
+/// field of about `2^127` give `c·127/2` bits against collisions, so a single
+/// element would only reach 64 bits and two are needed for 128.
+#[derive(Debug, Clone, Copy, Default)]
+#[allow(dead_code)]
+pub struct RescuePrime;
+
+const RESCUE_STATE_WIDTH: usize = 3;
+const RESCUE_CAPACITY: usize = 2;
+/// From the round count of the Rescue-Prime specification for 128 bits of
+/// security: the Gröbner basis attack on `l` rounds of a width `m`, rate `r`
+/// sponge has to solve a system of `m·(l - 1) + r` variables of degree
+/// `⌊(α - 1)·m·(l - 1)/2⌋ + 2`, which costs more than `2^128` from `l = 12`
+/// on for `m = 3` and `r = 1`. The specification then takes
+/// `⌈1.5·max(5, l)⌉ = 18` rounds to leave a margin.
+const RESCUE_ROUNDS: usize = 18;
+const RESCUE_ALPHA: usize = 3;
+const RESCUE_CHUNK_BYTES: usize = 15;
+const RESCUE_ELEMENT_BYTES: usize = 16;
+
+struct RescueParameters {
+    field: Field,
+    alpha_inverse: U256,
+    mds: [[FieldElement; RESCUE_STATE_WIDTH]; RESCUE_STATE_WIDTH],
+    round_constants: Vec<FieldElement>,
+}
+
+impl RescueParameters {
+    fn get() -> &'static RescueParameters {
+        static PARAMETERS: OnceLock<RescueParameters> = OnceLock::new();
+        PARAMETERS.get_or_init(|| {
+            let field = Field::new();
+            let p = field.modulus();
+
+            // p - 1 = 1 mod 3, so 3·(2(p - 1) + 1)/3 = 1 mod p - 1.
+            let alpha_inverse = ((p - U256::one()) * U256::from(2) + U256::one()) / U256::from(3);
+
+            // Cauchy matrix 1 / (x_i + y_j) with x = (0, 1, 2) and
+            // y = (3, 4, 5), which is MDS since the x and the y are distinct
+            // and no sum is zero.
+            let mut mds = [[field.zero(); RESCUE_STATE_WIDTH]; RESCUE_STATE_WIDTH];
+            for (i, row) in mds.iter_mut().enumerate() {
+                for (j, entry) in row.iter_mut().enumerate() {
+                    let sum = i + RESCUE_STATE_WIDTH + j;
+                    *entry = FieldElement::from_u64(sum as u64, field).inverse();
+                }
+            }
+
+            // Nothing-up-my-sleeve constants, expanded from the parameters.
+            let seed = format!(
+                "Rescue-Prime(p={}, m={}, c={}, N={})",
+                p, RESCUE_STATE_WIDTH, RESCUE_CAPACITY, RESCUE_ROUNDS
+            );
+            let count = 2 * RESCUE_STATE_WIDTH * RESCUE_ROUNDS;
+            let round_constants = Sha3::expand(seed.as_bytes(), 24 * count)
+                .chunks(24)
+                .map(|chunk| FieldElement::from_reduced(U256::from_big_endian(chunk), field))
+                .collect();
+
+            RescueParameters {
+                field,
+                alpha_inverse,
+                mds,
+                round_constants,
+            }
+        })
+    }
+
+    fn permute(&self, state: &mut [FieldElement; RESCUE_STATE_WIDTH]) {
+        for round in 0..RESCUE_ROUNDS {
+            let constants = &self.round_constants[2 * RESCUE_STATE_WIDTH * round..];
+
+            for s in state.iter_mut() {
+                *s = s.modexp(RESCUE_ALPHA);
+            }
+            self.mix(state, &constants[..RESCUE_STATE_WIDTH]);
+
+            for s in state.iter_mut() {
+                *s = s.modexp_u256(&self.alpha_inverse);
+            }
+            self.mix(
+                state,
+                &constants[RESCUE_STATE_WIDTH..2 * RESCUE_STATE_WIDTH],
+            );
+        }
+    }
+
+    fn mix(&self, state: &mut [FieldElement; RESCUE_STATE_WIDTH], constants: &[FieldElement]) {
+        let mut mixed = [self.field.zero(); RESCUE_STATE_WIDTH];
+        for (m, (row, c)) in mixed.iter_mut().zip(self.mds.iter().zip(constants)) {
+            *m = row
+                .iter()
+                .zip(state.iter())
+                .fold(*c, |acc, (a, s)| acc + *a * *s);
+        }
+        *state = mixed;
+    }
+}
+
+impl Hasher for RescuePrime {
+    const ID: HashId = HashId::RescuePrime;
+
+    fn hash(data: &[u8]) -> Digest {
+        let parameters = RescueParameters::get();
+        let field = parameters.field;
+        let mut state = [field.zero(); RESCUE_STATE_WIDTH];
+
+        // Sponge with a rate of one element, the first one, and the rest
+        // capacity. Padded with a single one.
+        let elements = data
+            .chunks(RESCUE_CHUNK_BYTES)
+            .map(|chunk| {
+                let marked = [&[1], chunk].concat();
+                FieldElement::new(U256::from_big_endian(&marked), field)
+            })
+            .chain(std::iter::once(field.one()));
+        for element in elements {
+            state[0] += element;
+            parameters.permute(&mut state);
+        }
+
+        let mut digest = [0; 32];
+        for half in digest.chunks_mut(RESCUE_ELEMENT_BYTES) {
+            let mut bytes = [0; 32];
+            state[0].value().to_big_endian(&mut bytes);
+            half.copy_from_slice(&bytes[32 - RESCUE_ELEMENT_BYTES..]);
+            parameters.permute(&mut state);
+        }
+
+        digest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(digest: &Digest) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Cross-checked against a straightforward Python implementation of the
+    /// same sponge, constants and MDS matrix.
+    #[test]
+    fn rescue_prime_known_answers() {
+        let cases: [(&[u8], &str); 3] = [
+            (
+                b"",
+                "a00cf1e6ef438187a950fb03b6641e0f62bd9c9363a834854dd6244e8142bc47",
+            ),
+            (
+                b"abc",
+                "878019aa557dc65a1eb9ba152aa6dcd6bade4c4c36bf3e1707e1d7ef315cacee",
+            ),
+            (
+                &[0; 40],
+                "b4fece74c1d2a78a21ce4002639aacd49a0f815ef4597ec74a96ff9f850472a2",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(hex(&RescuePrime::hash(input)), expected);
+        }
+    }
+
+    #[test]
+    fn rescue_prime_padding_separates_lengths() {
+        // Lengths on both sides of the chunk boundaries.
+        let lengths = [0, 1, 14, 15, 16, 30, 31];
+        let digests: Vec<Digest> = lengths
+            .iter()
+            .map(|len| RescuePrime::hash(&vec![0; *len]))
+            .collect();
+        for (i, a) in digests.iter().enumerate() {
+            assert!(
+                digests[i + 1..].iter().all(|b| a != b),
+                "{} zero bytes",
+                lengths[i]
+            );
+        }
+    }
+
+    #[test]
+    fn rescue_prime_s_boxes_invert_each_other() {
+        let parameters = RescueParameters::get();
+        let field = parameters.field;
+        for value in [0, 1, 2, 12345, u64::MAX] {
+            let x = FieldElement::from_u64(value, field);
+            assert_eq!(
+                x.modexp(RESCUE_ALPHA)
+                    .modexp_u256(&parameters.alpha_inverse),
+                x
+            );
+        }
+    }
+
+    /// The first `l` at which the Gröbner basis attack costs more than
+    /// `2^128`, with the binomial coefficient taken in logarithms.
+    #[test]
+    fn rescue_prime_round_count_follows_the_specification() {
+        let m = RESCUE_STATE_WIDTH;
+        let rate = m - RESCUE_CAPACITY;
+        let log2_binomial = |n: usize, k: usize| -> f64 {
+            (0..k)
+                .map(|i| ((n - i) as f64 / (i + 1) as f64).log2())
+                .sum()
+        };
+        let secure = |l: usize| {
+            let variables = m * (l - 1) + rate;
+            let degree = (RESCUE_ALPHA - 1) * m * (l - 1) / 2 + 2;
+            2.0 * log2_binomial(variables + degree, variables) > 128.0
+        };
+
+        let l = (1..).find(|l| secure(*l)).unwrap();
+        assert_eq!(l, 12);
+        assert_eq!(RESCUE_ROUNDS, (3 * l.max(5)).div_ceil(2));
+    }
+}
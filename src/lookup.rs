@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+
+use crate::{
+    algebra::{batch_inverse, Field, FieldElement},
+    constraint::{col, constant, Expression},
+};
+
+#[derive(Debug)]
+pub struct LookupError {
+    message: String,
+}
+
+impl fmt::Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl StdError for LookupError {}
+
+/// LogUp argument that every value of a trace column `f` occurs in a fixed
+/// table column `t`, with the multiplicity `m_j` of every table row
+/// committed as one more column. For a challenge `α` drawn after that
+/// commitment, the lookup holds iff
+///
+/// `Σ_i 1/(α - f_i) = Σ_j m_j/(α - t_j)`,
+///
+/// which the AIR enforces with a running sum column `S` accumulating the
+/// difference row by row and ending at zero. A range check, an opcode table
+/// or a character table is then one lookup instead of a bit decomposition.
+///
+/// All four columns are referred to by name, as in the constraint DSL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogUp {
+    pub column: String,
+    pub table: String,
+    pub multiplicity: String,
+    pub running_sum: String,
+}
+
+#[allow(dead_code)]
+impl LogUp {
+    pub fn new(column: &str, table: &str, multiplicity: &str, running_sum: &str) -> Self {
+        LogUp {
+            column: column.to_string(),
+            table: table.to_string(),
+            multiplicity: multiplicity.to_string(),
+            running_sum: running_sum.to_string(),
+        }
+    }
+
+    /// `S_0 = 1/(α - f_0) - m_0/(α - t_0)` with the denominators cleared.
+    pub fn first_row_constraint(&self, challenge: FieldElement) -> Expression {
+        self.step(col(&self.running_sum), challenge, false)
+    }
+
+    /// `S' - S = 1/(α - f') - m'/(α - t')` with the denominators cleared,
+    /// which has degree 3.
+    pub fn transition_constraint(&self, challenge: FieldElement) -> Expression {
+        let increment = col(&self.running_sum).next() - col(&self.running_sum);
+        self.step(increment, challenge, true)
+    }
+
+    /// `S_{n-1} = 0`: both sides of the sum agree.
+    pub fn last_row_constraint(&self) -> Expression {
+        col(&self.running_sum)
+    }
+
+    /// `increment·(α - f)(α - t) - (α - t) + m·(α - f)`, on the current or
+    /// the next row.
+    fn step(&self, increment: Expression, challenge: FieldElement, next: bool) -> Expression {
+        let column = |name: &str| if next { col(name).next() } else { col(name) };
+        let alpha = constant(challenge);
+        let looked_up = &alpha - column(&self.column);
+        let table = &alpha - column(&self.table);
+
+        increment * &looked_up * &table - &table + column(&self.multiplicity) * &looked_up
+    }
+}
+
+/// The table `0, 1, ..., 2^bits - 1` of a range check.
+#[allow(dead_code)]
+pub fn range_table(bits: u32, field: Field) -> Vec<FieldElement> {
+    (0..1u64 << bits)
+        .map(|v| FieldElement::from_u64(v, field))
+        .collect()
+}
+
+/// Pads `table` to `length` rows by repeating its first entry, which leaves
+/// the padding rows with multiplicity zero.
+#[allow(dead_code)]
+pub fn pad_table(table: &[FieldElement], length: usize) -> Vec<FieldElement> {
+    assert!(
+        !table.is_empty() && table.len() <= length,
+        "table has to fit the trace"
+    );
+    let mut padded = table.to_vec();
+    padded.resize(length, table[0]);
+
+    padded
+}
+
+/// How often every row of `table` is looked up by `column`. Repeated table
+/// entries are all counted against their first row.
+#[allow(dead_code)]
+pub fn multiplicities(
+    column: &[FieldElement],
+    table: &[FieldElement],
+) -> Result<Vec<FieldElement>, LookupError> {
+    let mut rows: HashMap<FieldElement, usize> = HashMap::new();
+    for (j, t) in table.iter().enumerate().rev() {
+        rows.insert(*t, j);
+    }
+
+    let mut counts = vec![0u64; table.len()];
+    for (i, f) in column.iter().enumerate() {
+        let j = rows.get(f).ok_or_else(|| LookupError {
+            message: format!("value {} in row {} is not in the table", f.bytes(), i),
+        })?;
+        counts[*j] += 1;
+    }
+
+    Ok(counts
+        .into_iter()
+        .zip(table)
+        .map(|(c, t)| FieldElement::from_u64(c, t.field))
+        .collect())
+}
+
+/// The running sum column `S_i = Σ_{k ≤ i} 1/(α - f_k) - m_k/(α - t_k)`, whose
+/// last entry is zero if the lookup holds and otherwise only with negligible
+/// probability. Panics if `α` hits a value of the column or of the table,
+/// which is just as unlikely.
+#[allow(dead_code)]
+pub fn running_sum(
+    column: &[FieldElement],
+    table: &[FieldElement],
+    multiplicities: &[FieldElement],
+    challenge: FieldElement,
+) -> Vec<FieldElement> {
+    assert!(
+        column.len() == table.len() && table.len() == multiplicities.len(),
+        "lookup columns not the same length"
+    );
+    let differences: Vec<FieldElement> =
+        column.iter().chain(table).map(|v| challenge - *v).collect();
+    let inverses = batch_inverse(&differences);
+    let (looked_up, table) = inverses.split_at(column.len());
+
+    let mut acc = challenge.field.zero();
+    looked_up
+        .iter()
+        .zip(table)
+        .zip(multiplicities)
+        .map(|((f, t), m)| {
+            acc += *f - *m * *t;
+            acc
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const LENGTH: usize = 16;
+    const COLUMNS: [&str; 4] = ["f", "t", "m", "s"];
+
+    fn logup() -> LogUp {
+        LogUp::new("f", "t", "m", "s")
+    }
+
+    /// Evaluates every constraint of the lookup on every row of the trace and
+    /// returns whether they all vanish.
+    fn constraints_hold(
+        column: &[FieldElement],
+        table: &[FieldElement],
+        multiplicities: &[FieldElement],
+        challenge: FieldElement,
+    ) -> bool {
+        let field = challenge.field;
+        let sums = running_sum(column, table, multiplicities, challenge);
+        let rows: Vec<Vec<FieldElement>> = (0..LENGTH)
+            .map(|i| vec![column[i], table[i], multiplicities[i], sums[i]])
+            .collect();
+        let lower = |e: Expression| e.lower(&COLUMNS, &field).unwrap();
+        let first = lower(logup().first_row_constraint(challenge));
+        let transition = lower(logup().transition_constraint(challenge));
+        let last = lower(logup().last_row_constraint());
+        let at = |i: usize, j: usize| [rows[i].as_slice(), rows[j].as_slice()].concat();
+
+        first.evaluate(&at(0, 1)).is_zero()
+            && (0..LENGTH - 1).all(|i| transition.evaluate(&at(i, i + 1)).is_zero())
+            && last.evaluate(&at(LENGTH - 1, 0)).is_zero()
+    }
+
+    #[test]
+    fn valid_lookups_satisfy_the_constraints() {
+        let field = Field::new();
+        let mut rng = testing::rng(42);
+        let table = pad_table(&range_table(3, field), LENGTH);
+        let column: Vec<FieldElement> = (0..LENGTH)
+            .map(|_| FieldElement::from_u64(rng.below(8) as u64, field))
+            .collect();
+        let counts = multiplicities(&column, &table).unwrap();
+        let challenge = rng.element();
+
+        assert_eq!(
+            counts.iter().fold(field.zero(), |acc, m| acc + *m),
+            FieldElement::from_u64(LENGTH as u64, field)
+        );
+        assert!(running_sum(&column, &table, &counts, challenge)[LENGTH - 1].is_zero());
+        assert!(constraints_hold(&column, &table, &counts, challenge));
+    }
+
+    #[test]
+    fn values_missing_from_the_table_are_rejected() {
+        let field = Field::new();
+        let mut rng = testing::rng(43);
+        let table = pad_table(&range_table(3, field), LENGTH);
+        let mut column: Vec<FieldElement> = (0..LENGTH)
+            .map(|_| FieldElement::from_u64(rng.below(8) as u64, field))
+            .collect();
+        let honest = multiplicities(&column, &table).unwrap();
+        column[5] = FieldElement::from_u64(9, field);
+
+        assert!(multiplicities(&column, &table).is_err());
+        // Reusing the multiplicities of the honest column leaves the running
+        // sum short of zero.
+        let challenge = rng.element();
+        assert!(!running_sum(&column, &table, &honest, challenge)[LENGTH - 1].is_zero());
+        assert!(!constraints_hold(&column, &table, &honest, challenge));
+    }
+}
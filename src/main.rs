@@ -1,24 +1,70 @@
-use bigint::U256;
-
 use crate::{
     algebra::{Field, FieldElement},
+    security::{SecurityParameters, USAGE},
     unipolynomial::UPolynomial,
-    multipolynomial::MPolynomial
 };
 
+mod air;
 mod algebra;
+mod constraint;
+mod deep;
+mod domain;
+mod evaluation;
+mod hash;
+mod lookup;
 mod unipolynomial;
+mod merkle;
+mod multilinear;
 mod multipolynomial;
+mod multitable;
+mod ntt;
+mod parallel;
+mod periodic;
+mod preprocessed;
+mod proof_stream;
+mod random;
+mod security;
+mod storage;
+mod subproduct;
+mod sumcheck;
+#[cfg(test)]
+mod testing;
+mod vanishing;
+mod zk;
 
 fn main() {
-    let p: U256 = U256::from_dec_str("270497897142230380135924736767050121217").unwrap();
-    let field = Field::new(p);
+    let field = Field::new();
+
+    // Defaults for whatever isn't given on the command line.
+    let defaults = SecurityParameters {
+        field_bits: field.bits() as u32,
+        expansion_factor: 8,
+        num_queries: 48,
+        grinding_bits: 16,
+        air_degree: 3,
+        trace_length: 1 << 12,
+        num_columns: 4,
+        hash_bits: 256,
+        target_bits: 100,
+    };
+    let parameters = match defaults.with_args(std::env::args().skip(1)) {
+        Ok(parameters) => parameters,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    println!("{}", parameters);
+    if let Err(e) = parameters.check() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 
-    let a = FieldElement::new(U256::from(1), field);
-    let b = FieldElement::new(U256::from(2), field);
-    let c = FieldElement::new(U256::from(3), field);
-    let d = FieldElement::new(U256::from(4), field);
-    let e = FieldElement::new(U256::from(5), field);
+    let a = FieldElement::from_u64(1, field);
+    let b = FieldElement::from_u64(2, field);
+    let c = FieldElement::from_u64(3, field);
+    let d = FieldElement::from_u64(4, field);
+    let e = FieldElement::from_u64(5, field);
 
     let f_coefficients = [a, b, c, d, e].to_vec();
     let g_coefficients = [a, b, c, d, e].to_vec();
@@ -0,0 +1,575 @@
+use std::{collections::BTreeMap, marker::PhantomData};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    algebra::FieldElement,
+    hash::{Digest, Hasher, Sha3},
+    parallel::*,
+};
+
+/// Prefixes keeping leaf hashes and inner node hashes apart.
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// Authentication data for a batch of opened leaves. The variant is part of
+/// the serialized proof, so the verifier always knows how to read it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MerkleProof {
+    /// One full authentication path per opened leaf, in the order the leaves
+    /// were requested.
+    Individual(Vec<Vec<Digest>>),
+    /// Only the sibling nodes that can't be recomputed from the opened leaves
+    /// themselves, layer by layer bottom up and left to right within a layer.
+    /// Openings of nearby leaves share most of their upper nodes, so this is
+    /// much smaller once more than a handful of leaves are opened.
+    Batched(Vec<Digest>),
+}
+
+/// Binary Merkle tree over a power-of-two number of leaves. Layer `0` holds
+/// the leaf hashes and the last layer holds the root.
+///
+/// Leaves can be salted, in which case every leaf hash also covers a random
+/// salt that is revealed only along with that leaf. Without salts, a low
+/// entropy leaf can be recovered from its hash alone, which breaks zero
+/// knowledge for the leaves that are never opened.
+///
+/// Nodes are hashed with `H`, which has to match the hash of the proof stream
+/// the root is sent over.
+#[derive(Debug, Clone)]
+pub struct MerkleTree<H = Sha3> {
+    layers: Vec<Vec<Digest>>,
+    salts: Option<Vec<Digest>>,
+    hasher: PhantomData<H>,
+}
+
+#[allow(dead_code)]
+impl<H: Hasher> MerkleTree<H> {
+    pub fn commit(leaves: &[Vec<u8>]) -> Self {
+        Self::build(leaves, None)
+    }
+
+    pub fn commit_salted(leaves: &[Vec<u8>], salts: Vec<Digest>) -> Self {
+        assert!(
+            leaves.len() == salts.len(),
+            "every leaf needs exactly one salt"
+        );
+        Self::build(leaves, Some(salts))
+    }
+
+    fn build(leaves: &[Vec<u8>], salts: Option<Vec<Digest>>) -> Self {
+        assert!(
+            leaves.len().is_power_of_two(),
+            "number of leaves has to be a power of two"
+        );
+
+        let hashes = iter!(leaves)
+            .enumerate()
+            .map(|(i, leaf)| hash_leaf::<H>(leaf, salts.as_ref().map(|s| &s[i])))
+            .collect();
+        let mut layers: Vec<Vec<Digest>> = vec![hashes];
+        while layers[layers.len() - 1].len() > 1 {
+            let parents = chunks!(layers[layers.len() - 1], 2)
+                .map(|pair| hash_node::<H>(&pair[0], &pair[1]))
+                .collect();
+            layers.push(parents);
+        }
+
+        MerkleTree {
+            layers,
+            salts,
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn root(&self) -> Digest {
+        self.layers[self.layers.len() - 1][0]
+    }
+
+    pub fn num_leaves(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    /// Returns the salt of leaf `index`, which has to travel with its opening.
+    pub fn salt(&self, index: usize) -> Option<Digest> {
+        self.salts.as_ref().map(|salts| salts[index])
+    }
+
+    /// Returns the authentication path of leaf `index`, bottom up.
+    pub fn open(&self, index: usize) -> Vec<Digest> {
+        assert!(index < self.num_leaves(), "leaf index out of range");
+        self.layers[..self.layers.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(depth, layer)| layer[(index >> depth) ^ 1])
+            .collect()
+    }
+
+    /// Opens every leaf in `indices` with one authentication path each.
+    pub fn open_individual(&self, indices: &[usize]) -> MerkleProof {
+        MerkleProof::Individual(indices.iter().map(|i| self.open(*i)).collect())
+    }
+
+    /// Opens every leaf in `indices` at once, sending each sibling node needed
+    /// to recompute the root exactly once.
+    pub fn open_many(&self, indices: &[usize]) -> MerkleProof {
+        assert!(
+            indices.iter().all(|i| *i < self.num_leaves()),
+            "leaf index out of range"
+        );
+
+        MerkleProof::Batched(
+            sibling_indices(self.num_leaves(), indices)
+                .iter()
+                .zip(&self.layers)
+                .flat_map(|(siblings, layer)| siblings.iter().map(|i| layer[*i]))
+                .collect(),
+        )
+    }
+
+    /// Checks a batch opening in either encoding of a tree with `num_leaves`
+    /// leaves. `leaves[i]` (and `salts[i]` for a salted tree) belong to
+    /// `indices[i]`.
+    pub fn verify_many(
+        root: &Digest,
+        num_leaves: usize,
+        indices: &[usize],
+        leaves: &[Vec<u8>],
+        salts: Option<&[Digest]>,
+        proof: &MerkleProof,
+    ) -> bool {
+        if indices.len() != leaves.len()
+            || salts.is_some_and(|s| s.len() != indices.len())
+            || !num_leaves.is_power_of_two()
+            || indices.iter().any(|i| *i >= num_leaves)
+        {
+            return false;
+        }
+        let depth = num_leaves.trailing_zeros() as usize;
+        let salt = |i: usize| salts.map(|s| &s[i]);
+
+        match proof {
+            MerkleProof::Individual(paths) => {
+                paths.len() == indices.len()
+                    && paths.iter().enumerate().all(|(i, path)| {
+                        path.len() == depth
+                            && Self::verify(root, indices[i], path, &leaves[i], salt(i))
+                    })
+            }
+            MerkleProof::Batched(siblings) => {
+                let mut nodes: Vec<(usize, Digest)> = indices
+                    .iter()
+                    .enumerate()
+                    .map(|(i, index)| (*index, hash_leaf::<H>(&leaves[i], salt(i))))
+                    .collect();
+                nodes.sort_unstable();
+                nodes.dedup();
+                if nodes.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+                    // The same leaf opened to two different values.
+                    return false;
+                }
+
+                let mut siblings = siblings.iter();
+                for _ in 0..depth {
+                    let mut parents = Vec::with_capacity(nodes.len());
+                    let mut i = 0;
+                    while i < nodes.len() {
+                        let (index, hash) = nodes[i];
+                        let parent = if i + 1 < nodes.len() && nodes[i + 1].0 == index ^ 1 {
+                            i += 2;
+                            hash_node::<H>(&hash, &nodes[i - 1].1)
+                        } else {
+                            i += 1;
+                            match siblings.next() {
+                                Some(sibling) if index & 1 == 0 => hash_node::<H>(&hash, sibling),
+                                Some(sibling) => hash_node::<H>(sibling, &hash),
+                                None => return false,
+                            }
+                        };
+                        parents.push((index >> 1, parent));
+                    }
+                    nodes = parents;
+                }
+
+                siblings.next().is_none() && nodes == [(0, *root)]
+            }
+        }
+    }
+
+    pub fn verify(
+        root: &Digest,
+        index: usize,
+        path: &[Digest],
+        leaf: &[u8],
+        salt: Option<&Digest>,
+    ) -> bool {
+        if index >> path.len() != 0 {
+            return false;
+        }
+
+        let mut acc = hash_leaf::<H>(leaf, salt);
+        for (depth, sibling) in path.iter().enumerate() {
+            acc = if (index >> depth) & 1 == 0 {
+                hash_node::<H>(&acc, sibling)
+            } else {
+                hash_node::<H>(sibling, &acc)
+            };
+        }
+
+        acc == *root
+    }
+}
+
+/// Computes the root of a `MerkleTree` from its leaves fed in order, keeping
+/// one pending node per layer instead of the whole tree. Leaves are either
+/// all pushed with `push` or all with `push_salted`, as they would be
+/// committed with `commit` or `commit_salted`.
+///
+/// Built with `with_openings`, it also picks up the siblings of a batch
+/// opening on the way, and the salts of the opened leaves, so leaves too many
+/// to hold at once can still be opened by feeding them a second time.
+#[derive(Debug, Clone)]
+pub struct MerkleRootBuilder<H = Sha3> {
+    pending: Vec<Option<Digest>>,
+    num_leaves: usize,
+    /// Leaves passed to `with_openings`, in the order they were requested.
+    indices: Vec<usize>,
+    /// Per layer, the siblings to send and the ones found so far.
+    openings: Vec<BTreeMap<usize, Option<Digest>>>,
+    /// Salts of the opened leaves and the ones found so far.
+    salts: BTreeMap<usize, Option<Digest>>,
+    salted: bool,
+    hasher: PhantomData<H>,
+}
+
+impl<H: Hasher> Default for MerkleRootBuilder<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: Hasher> MerkleRootBuilder<H> {
+    pub fn new() -> Self {
+        MerkleRootBuilder {
+            pending: Vec::new(),
+            num_leaves: 0,
+            indices: Vec::new(),
+            openings: Vec::new(),
+            salts: BTreeMap::new(),
+            salted: false,
+            hasher: PhantomData,
+        }
+    }
+
+    /// Collects the opening of `indices` in a tree of `num_leaves` leaves,
+    /// which `proof` returns once every leaf is pushed.
+    pub fn with_openings(num_leaves: usize, indices: &[usize]) -> Self {
+        assert!(
+            num_leaves.is_power_of_two(),
+            "number of leaves has to be a power of two"
+        );
+        assert!(
+            indices.iter().all(|i| *i < num_leaves),
+            "leaf index out of range"
+        );
+
+        MerkleRootBuilder {
+            indices: indices.to_vec(),
+            salts: indices.iter().map(|i| (*i, None)).collect(),
+            openings: sibling_indices(num_leaves, indices)
+                .into_iter()
+                .map(|siblings| siblings.into_iter().map(|i| (i, None)).collect())
+                .collect(),
+            ..Self::new()
+        }
+    }
+
+    pub fn push(&mut self, leaves: &[Vec<u8>]) {
+        let hashes: Vec<Digest> = iter!(leaves)
+            .map(|leaf| hash_leaf::<H>(leaf, None))
+            .collect();
+        self.push_hashes(hashes);
+    }
+
+    /// Pushes `leaves`, each hashed along with its salt in `salts`.
+    pub fn push_salted(&mut self, leaves: &[Vec<u8>], salts: &[Digest]) {
+        assert!(
+            leaves.len() == salts.len(),
+            "every leaf needs exactly one salt"
+        );
+        let hashes: Vec<Digest> = iter!(leaves)
+            .zip(salts)
+            .map(|(leaf, salt)| hash_leaf::<H>(leaf, Some(salt)))
+            .collect();
+        for (i, salt) in salts.iter().enumerate() {
+            if let Some(opened) = self.salts.get_mut(&(self.num_leaves + i)) {
+                *opened = Some(*salt);
+            }
+        }
+        self.salted = true;
+        self.push_hashes(hashes);
+    }
+
+    fn push_hashes(&mut self, hashes: Vec<Digest>) {
+        for hash in hashes {
+            let mut acc = hash;
+            let mut depth = 0;
+            self.record(depth, acc);
+            while let Some(left) = self.pending.get_mut(depth).and_then(Option::take) {
+                acc = hash_node::<H>(&left, &acc);
+                depth += 1;
+                self.record(depth, acc);
+            }
+            if depth == self.pending.len() {
+                self.pending.push(None);
+            }
+            self.pending[depth] = Some(acc);
+            self.num_leaves += 1;
+        }
+    }
+
+    pub fn root(&self) -> Digest {
+        assert!(
+            self.num_leaves.is_power_of_two(),
+            "number of leaves has to be a power of two"
+        );
+        self.pending[self.pending.len() - 1].unwrap()
+    }
+
+    /// The batch opening `MerkleTree::open_many` gives for the indices passed
+    /// to `with_openings`.
+    pub fn proof(&self) -> MerkleProof {
+        assert!(
+            self.num_leaves == 1 << self.openings.len(),
+            "opening needs every leaf of the tree"
+        );
+
+        MerkleProof::Batched(
+            self.openings
+                .iter()
+                .flat_map(|layer| layer.values().map(|sibling| sibling.unwrap()))
+                .collect(),
+        )
+    }
+
+    /// Salts of the leaves passed to `with_openings`, in the same order, which
+    /// `MerkleTree::verify_many` needs along with `proof`. `None` for an
+    /// unsalted tree.
+    pub fn salts(&self) -> Option<Vec<Digest>> {
+        assert!(
+            self.num_leaves == 1 << self.openings.len(),
+            "opening needs every leaf of the tree"
+        );
+        self.salted.then(|| {
+            self.indices
+                .iter()
+                .map(|i| self.salts[i].unwrap())
+                .collect()
+        })
+    }
+
+    /// Keeps the node at `depth` that the leaf just pushed completes, if it's
+    /// one of the siblings to send.
+    fn record(&mut self, depth: usize, node: Digest) {
+        let index = self.num_leaves >> depth;
+        if let Some(sibling) = self
+            .openings
+            .get_mut(depth)
+            .and_then(|layer| layer.get_mut(&index))
+        {
+            *sibling = Some(node);
+        }
+    }
+}
+
+/// Per layer, bottom up, the sibling nodes a batch opening of `indices` has
+/// to send because they can't be recomputed from the opened leaves.
+fn sibling_indices(num_leaves: usize, indices: &[usize]) -> Vec<Vec<usize>> {
+    let mut known: Vec<usize> = indices.to_vec();
+    known.sort_unstable();
+    known.dedup();
+
+    let mut layers = Vec::new();
+    for _ in 0..num_leaves.trailing_zeros() {
+        let mut siblings = Vec::new();
+        let mut parents = Vec::with_capacity(known.len());
+        let mut i = 0;
+        while i < known.len() {
+            let index = known[i];
+            if i + 1 < known.len() && known[i + 1] == index ^ 1 {
+                i += 2;
+            } else {
+                siblings.push(index ^ 1);
+                i += 1;
+            }
+            parents.push(index >> 1);
+        }
+        layers.push(siblings);
+        known = parents;
+    }
+
+    layers
+}
+
+/// Encodes a row of field elements as a leaf.
+pub fn leaf_bytes(elements: &[FieldElement]) -> Vec<u8> {
+    bincode::serialize(elements).unwrap()
+}
+
+fn hash_leaf<H: Hasher>(leaf: &[u8], salt: Option<&Digest>) -> Digest {
+    let salt: &[u8] = salt.map_or(&[], |s| s.as_slice());
+    H::hash(&[&[LEAF_PREFIX], salt, leaf].concat())
+}
+
+fn hash_node<H: Hasher>(left: &Digest, right: &Digest) -> Digest {
+    H::hash(&[&[NODE_PREFIX], left.as_slice(), right.as_slice()].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const NUM_LEAVES: usize = 256;
+
+    fn leaves() -> Vec<Vec<u8>> {
+        (0..NUM_LEAVES as u64)
+            .map(|i| i.to_le_bytes().to_vec())
+            .collect()
+    }
+
+    fn num_digests(proof: &MerkleProof) -> usize {
+        match proof {
+            MerkleProof::Individual(paths) => paths.iter().map(Vec::len).sum(),
+            MerkleProof::Batched(siblings) => siblings.len(),
+        }
+    }
+
+    /// 64 distinct random leaves.
+    fn indices() -> Vec<usize> {
+        let mut rng = testing::rng(34);
+        let mut indices = Vec::new();
+        while indices.len() < 64 {
+            let index = rng.below(NUM_LEAVES);
+            if !indices.contains(&index) {
+                indices.push(index);
+            }
+        }
+
+        indices
+    }
+
+    #[test]
+    fn openings_round_trip() {
+        let leaves = leaves();
+        let mut rng = testing::rng(35);
+        let salts: Vec<Digest> = (0..NUM_LEAVES).map(|_| rng.digest()).collect();
+        let indices = indices();
+        let opened: Vec<Vec<u8>> = indices.iter().map(|i| leaves[*i].clone()).collect();
+        let opened_salts: Vec<Digest> = indices.iter().map(|i| salts[*i]).collect();
+
+        for (tree, salts) in [
+            (MerkleTree::<Sha3>::commit(&leaves), None),
+            (
+                MerkleTree::<Sha3>::commit_salted(&leaves, salts),
+                Some(opened_salts.as_slice()),
+            ),
+        ] {
+            let root = tree.root();
+            for proof in [tree.open_many(&indices), tree.open_individual(&indices)] {
+                assert!(MerkleTree::<Sha3>::verify_many(
+                    &root, NUM_LEAVES, &indices, &opened, salts, &proof
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn tampered_openings_are_rejected() {
+        let leaves = leaves();
+        let tree = MerkleTree::<Sha3>::commit(&leaves);
+        let root = tree.root();
+        let indices = indices();
+        let opened: Vec<Vec<u8>> = indices.iter().map(|i| leaves[*i].clone()).collect();
+        let verify = |indices: &[usize], opened: &[Vec<u8>], proof: &MerkleProof| {
+            MerkleTree::<Sha3>::verify_many(&root, NUM_LEAVES, indices, opened, None, proof)
+        };
+
+        let MerkleProof::Batched(siblings) = tree.open_many(&indices) else {
+            unreachable!()
+        };
+        for i in [0, siblings.len() / 2, siblings.len() - 1] {
+            let mut tampered = siblings.clone();
+            tampered[i][0] ^= 1;
+            assert!(!verify(&indices, &opened, &MerkleProof::Batched(tampered)));
+        }
+        let mut truncated = siblings.clone();
+        truncated.pop();
+        assert!(!verify(&indices, &opened, &MerkleProof::Batched(truncated)));
+        let mut extended = siblings.clone();
+        extended.push(siblings[0]);
+        assert!(!verify(&indices, &opened, &MerkleProof::Batched(extended)));
+
+        let MerkleProof::Individual(mut paths) = tree.open_individual(&indices) else {
+            unreachable!()
+        };
+        paths[3][5][0] ^= 1;
+        assert!(!verify(&indices, &opened, &MerkleProof::Individual(paths)));
+
+        let mut wrong_leaf = opened.clone();
+        wrong_leaf[7][0] ^= 1;
+        assert!(!verify(&indices, &wrong_leaf, &tree.open_many(&indices)));
+
+        // The same leaf opened twice has to open to the same value.
+        let mut repeated = indices.clone();
+        repeated.push(indices[0]);
+        let mut conflicting = opened.clone();
+        conflicting.push(leaves[indices[0] ^ 1].clone());
+        assert!(!verify(&repeated, &conflicting, &tree.open_many(&repeated)));
+    }
+
+    #[test]
+    fn batched_openings_share_siblings() {
+        let tree = MerkleTree::<Sha3>::commit(&leaves());
+        let indices = indices();
+
+        assert_eq!(num_digests(&tree.open_individual(&indices)), 64 * 8);
+        assert_eq!(num_digests(&tree.open_many(&indices)), 92);
+
+        // A single leaf needs its whole path, a full subtree only the path of
+        // its root, and the whole tree nothing.
+        assert_eq!(num_digests(&tree.open_many(&[5])), 8);
+        let subtree: Vec<usize> = (64..128).collect();
+        assert_eq!(num_digests(&tree.open_many(&subtree)), 2);
+        let all: Vec<usize> = (0..NUM_LEAVES).collect();
+        assert_eq!(num_digests(&tree.open_many(&all)), 0);
+    }
+
+    #[test]
+    fn streamed_openings_match_the_tree() {
+        let leaves = leaves();
+        let tree = MerkleTree::<Sha3>::commit(&leaves);
+
+        let mut rng = testing::rng(36);
+        let salts: Vec<Digest> = (0..NUM_LEAVES).map(|_| rng.digest()).collect();
+        let salted = MerkleTree::<Sha3>::commit_salted(&leaves, salts.clone());
+
+        for indices in [indices(), vec![5], vec![0, 255, 0], vec![]] {
+            let mut builder = MerkleRootBuilder::<Sha3>::with_openings(NUM_LEAVES, &indices);
+            let mut salted_builder = MerkleRootBuilder::<Sha3>::with_openings(NUM_LEAVES, &indices);
+            for (window, salts) in leaves.chunks(24).zip(salts.chunks(24)) {
+                builder.push(window);
+                salted_builder.push_salted(window, salts);
+            }
+            assert_eq!(builder.root(), tree.root());
+            assert_eq!(builder.proof(), tree.open_many(&indices));
+            assert_eq!(builder.salts(), None);
+
+            assert_eq!(salted_builder.root(), salted.root());
+            assert_eq!(salted_builder.proof(), salted.open_many(&indices));
+            let opened_salts: Vec<Digest> =
+                indices.iter().map(|i| salted.salt(*i).unwrap()).collect();
+            assert_eq!(salted_builder.salts(), Some(opened_salts));
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{
+    algebra::{Field, FieldElement},
+    multipolynomial::MPolynomial,
+};
+
+/// The unique multilinear polynomial in `n` variables taking the values of a
+/// table of `2^n` entries on the boolean hypercube.
+///
+/// Entry `i` of the table is the value at the point whose coordinates are the
+/// bits of `i`, most significant bit first: variable `0` selects the upper or
+/// lower half of the table, variable `n - 1` the parity of the index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultilinearExtension {
+    num_variables: usize,
+    values: Vec<FieldElement>,
+}
+
+#[allow(dead_code)]
+impl MultilinearExtension {
+    pub fn new(values: Vec<FieldElement>) -> Self {
+        assert!(
+            values.len().is_power_of_two(),
+            "table size has to be a power of two"
+        );
+
+        MultilinearExtension {
+            num_variables: values.len().trailing_zeros() as usize,
+            values,
+        }
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.num_variables
+    }
+
+    pub fn values(&self) -> &[FieldElement] {
+        &self.values
+    }
+
+    /// Evaluates the extension at any point in `O(2^n)` by fixing one
+    /// variable after the other.
+    pub fn evaluate(&self, point: &[FieldElement]) -> FieldElement {
+        assert!(
+            point.len() == self.num_variables,
+            "point needs a coordinate for every variable"
+        );
+
+        let mut table = self.values.clone();
+        for r in point {
+            let half = table.len() / 2;
+            for i in 0..half {
+                table[i] = table[i] + *r * (table[i + half] - table[i]);
+            }
+            table.truncate(half);
+        }
+
+        table[0]
+    }
+
+    /// Fixes variable `0` to `r`, which leaves an extension in the remaining
+    /// `n - 1` variables.
+    pub fn fix_first_variable(&self, r: &FieldElement) -> Self {
+        assert!(self.num_variables > 0, "no variable left to fix");
+        let half = self.values.len() / 2;
+        let values = (0..half)
+            .map(|i| self.values[i] + *r * (self.values[i + half] - self.values[i]))
+            .collect();
+
+        MultilinearExtension {
+            num_variables: self.num_variables - 1,
+            values,
+        }
+    }
+
+    /// The extension in coefficient form, through the Möbius transform of the
+    /// table.
+    pub fn to_polynomial(&self) -> MPolynomial {
+        let n = self.num_variables;
+        let mut coefficients = self.values.clone();
+        for bit in 0..n {
+            for i in 0..coefficients.len() {
+                if i & (1 << bit) != 0 {
+                    coefficients[i] = coefficients[i] - coefficients[i ^ (1 << bit)];
+                }
+            }
+        }
+
+        MPolynomial::new(coefficients.into_iter().enumerate().map(|(i, c)| {
+            let exponents = (0..n).map(|j| ((i >> (n - 1 - j)) & 1) as u128).collect();
+            (exponents, c)
+        }))
+    }
+
+    /// Tabulates a multilinear `polynomial` over the hypercube, or returns
+    /// `None` if some variable occurs with an exponent above one.
+    pub fn from_polynomial(polynomial: &MPolynomial, field: &Field) -> Option<Self> {
+        let n = polynomial.num_variables();
+        let mut values = vec![field.zero(); 1 << n];
+        for (exponents, coefficient) in polynomial.terms() {
+            let mut index = 0;
+            for e in exponents {
+                if *e > 1 {
+                    return None;
+                }
+                index = (index << 1) | *e as usize;
+            }
+            values[index] = *coefficient;
+        }
+
+        for bit in 0..n {
+            for i in 0..values.len() {
+                if i & (1 << bit) != 0 {
+                    values[i] = values[i] + values[i ^ (1 << bit)];
+                }
+            }
+        }
+
+        Some(MultilinearExtension {
+            num_variables: n,
+            values,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn extension_interpolates_the_table() {
+        let field = Field::new();
+        let mut rng = testing::rng(41);
+        for n in 1..5 {
+            let extension = MultilinearExtension::new(rng.elements(1 << n));
+            for (i, value) in extension.values().iter().enumerate() {
+                let point: Vec<FieldElement> = (0..n)
+                    .map(|j| FieldElement::from_u64(((i >> (n - 1 - j)) & 1) as u64, field))
+                    .collect();
+                assert_eq!(extension.evaluate(&point), *value);
+            }
+
+            let polynomial = extension.to_polynomial();
+            assert!((0..n).all(|var| polynomial.degree_in(var) <= 1));
+            assert_eq!(
+                MultilinearExtension::from_polynomial(&polynomial, &field),
+                Some(extension.clone())
+            );
+
+            let point = rng.elements(n);
+            assert_eq!(extension.evaluate(&point), polynomial.evaluate(&point));
+            assert_eq!(
+                extension
+                    .fix_first_variable(&point[0])
+                    .evaluate(&point[1..]),
+                extension.evaluate(&point)
+            );
+        }
+    }
+}
@@ -1,59 +1,41 @@
-use std::cmp::max;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::ops;
 
+use serde::{Deserialize, Serialize};
+
 use crate::algebra::{Field, FieldElement};
 
-#[derive(Debug, Clone)]
+/// Largest number of terms the multinomial theorem may produce before `pow`
+/// falls back to square-and-multiply.
+const MULTINOMIAL_EXPANSION_LIMIT: u128 = 1 << 12;
+
+/// Largest exponent expanded with the multinomial theorem, which keeps every
+/// binomial coefficient within a `u64`.
+const MULTINOMIAL_MAX_EXPONENT: u128 = 32;
+
+/// Sparse multivariate polynomial in canonical form: every exponent vector
+/// has exactly `num_variables` entries, no coefficient is zero, and terms are
+/// kept sorted by exponent vector. Two polynomials are equal exactly when
+/// they have the same arity and terms, and iterating, serializing or hashing
+/// one is deterministic, so constraint systems can go into a transcript.
+///
+/// Arithmetic between polynomials of different arity treats the missing
+/// trailing variables of the smaller one as absent.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct MPolynomial {
+    num_variables: usize,
     // vector of exponents : coefficients
-    dictionary: HashMap<Vec<u128>, FieldElement>,
+    terms: BTreeMap<Vec<u128>, FieldElement>,
 }
 
 impl ops::Add for MPolynomial {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        let mut dictionary: HashMap<Vec<u128>, FieldElement> = HashMap::new();
-        let max_self = self
-            .dictionary
-            .keys()
-            .into_iter()
-            .map(|k| k.len())
-            .max()
-            .unwrap();
-        let max_rhs = rhs
-            .dictionary
-            .keys()
-            .into_iter()
-            .map(|k| k.len())
-            .max()
-            .unwrap();
-        let num_variables = max(max_self, max_rhs);
-
-        for (k, v) in self.dictionary.iter() {
-            let pad_zeroes: Vec<u128> = (0..num_variables - k.len())
-                .map(|_| 0)
-                .collect();
-            let new_key = [k.as_slice(), pad_zeroes.as_slice()].concat();
-            dictionary.insert(new_key, *v);
-        }
-
-        for (k, v) in rhs.dictionary.iter() {
-            let pad_zeroes: Vec<u128> = (0..num_variables - k.len())
-                .map(|_| 0)
-                .collect();
-            let new_key = &[k.as_slice(), pad_zeroes.as_slice()].concat();
-
-            if dictionary.contains_key(new_key) {
-                let new_val = dictionary[new_key] + *v;
-                dictionary.insert(new_key.to_owned(), new_val);
-            } else {
-                dictionary.insert(new_key.to_owned(), v.to_owned());
-            }
-        }
+        let num_variables = self.num_variables.max(rhs.num_variables);
+        let terms = self.terms.into_iter().chain(rhs.terms);
 
-        MPolynomial { dictionary }
+        MPolynomial::with_arity(num_variables, terms)
     }
 }
 
@@ -61,46 +43,23 @@ impl ops::Mul for MPolynomial {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        let mut dictionary = HashMap::new();
-        let max_self = self
-            .dictionary
-            .keys()
-            .into_iter()
-            .map(|k| k.len())
-            .max()
-            .unwrap();
-        let max_rhs = rhs
-            .dictionary
-            .keys()
-            .into_iter()
-            .map(|k| k.len())
-            .max()
-            .unwrap();
-        let num_variables = max(max_self, max_rhs);
+        let num_variables = self.num_variables.max(rhs.num_variables);
+        let mut terms = Vec::with_capacity(self.terms.len() * rhs.terms.len());
 
-        for (k0, v0) in self.dictionary.iter() {
-            for (k1, v1) in rhs.dictionary.iter() {
-                let mut exponent: Vec<u128> =
-                    (0..num_variables).map(|_| 0).collect();
-
-                for k in 0..k0.len() {
-                    exponent[k] += k0[k];
-                }
-                for k in 0..k1.len() {
-                    exponent[k] += k1[k];
+        for (k0, v0) in self.terms.iter() {
+            for (k1, v1) in rhs.terms.iter() {
+                let mut exponent = vec![0; num_variables];
+                for (e, k) in exponent.iter_mut().zip(k0) {
+                    *e += k;
                 }
-
-                if dictionary.contains_key(&exponent) {
-                    let new_val = dictionary[&exponent] + v0.to_owned() * v1.to_owned();
-                    dictionary.insert(exponent, new_val);
-                } else {
-                    let new_val = v0.to_owned() * v1.to_owned();
-                    dictionary.insert(exponent, new_val);
+                for (e, k) in exponent.iter_mut().zip(k1) {
+                    *e += k;
                 }
+                terms.push((exponent, *v0 * *v1));
             }
         }
 
-        MPolynomial { dictionary }
+        MPolynomial::with_arity(num_variables, terms)
     }
 }
 
@@ -116,72 +75,410 @@ impl ops::Neg for MPolynomial {
     type Output = Self;
 
     fn neg(self) -> Self::Output {
-        let mut dictionary = HashMap::new();
-        for (k, v) in self.dictionary.iter() {
-            dictionary.insert(k.to_owned(), -(v.to_owned()));
+        MPolynomial {
+            num_variables: self.num_variables,
+            terms: self.terms.into_iter().map(|(k, v)| (k, -v)).collect(),
         }
-
-        MPolynomial { dictionary }
     }
 }
 
-impl ops::BitXor for MPolynomial {
+impl ops::BitXor<u128> for MPolynomial {
     type Output = Self;
 
-    fn bitxor(self, rhs: Self) -> Self::Output {
-        if self.is_zero() {
-            MPolynomial {
-                dictionary: HashMap::new(),
-            }
-        } else {
-            let pair = self.dictionary.iter().next().unwrap();
-            let field = pair.1.field;
-            let num_variables = pair.0.len();
-            let exp: Vec<u128> = (0..num_variables).map(|_| 0).collect();
-
-            let mut dictionary = HashMap::new();
-            dictionary.insert(exp.to_owned(), field.one());
-            let mut acc = MPolynomial { dictionary };
-
-            for val in exp.iter().skip(2) {
-                acc = acc.clone() * acc;
-                if *val == 1 {
-                    acc = acc * self.clone();
+    fn bitxor(self, exponent: u128) -> Self::Output {
+        self.pow(exponent)
+    }
+}
+
+impl MPolynomial {
+    /// Builds the canonical form of a sum of terms, padding exponent vectors
+    /// to the longest one, merging repeated monomials and dropping zeros.
+    pub fn new(terms: impl IntoIterator<Item = (Vec<u128>, FieldElement)>) -> Self {
+        let terms: Vec<(Vec<u128>, FieldElement)> = terms.into_iter().collect();
+        let num_variables = terms.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+
+        MPolynomial::with_arity(num_variables, terms)
+    }
+
+    /// The zero polynomial in `num_variables` variables.
+    pub fn zero(num_variables: usize) -> Self {
+        MPolynomial {
+            num_variables,
+            terms: BTreeMap::new(),
+        }
+    }
+
+    pub fn constant(num_variables: usize, element: FieldElement) -> Self {
+        MPolynomial::with_arity(num_variables, [(vec![0; num_variables], element)])
+    }
+
+    /// Returns the polynomials `X_0, ..., X_{n-1}` in `n` variables.
+    pub fn variables(num_variables: usize, field: &Field) -> Vec<Self> {
+        (0..num_variables)
+            .map(|i| {
+                let mut exponents = vec![0; num_variables];
+                exponents[i] = 1;
+
+                MPolynomial::with_arity(num_variables, [(exponents, field.one())])
+            })
+            .collect()
+    }
+
+    fn with_arity(
+        num_variables: usize,
+        terms: impl IntoIterator<Item = (Vec<u128>, FieldElement)>,
+    ) -> Self {
+        let mut acc: BTreeMap<Vec<u128>, FieldElement> = BTreeMap::new();
+        for (mut exponents, coefficient) in terms {
+            assert!(
+                exponents.len() <= num_variables,
+                "monomial has more variables than the polynomial"
+            );
+            exponents.resize(num_variables, 0);
+            match acc.get_mut(&exponents) {
+                Some(c) => *c += coefficient,
+                None => {
+                    acc.insert(exponents, coefficient);
                 }
             }
+        }
+        acc.retain(|_, c| !c.is_zero());
+
+        MPolynomial {
+            num_variables,
+            terms: acc,
+        }
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.num_variables
+    }
+
+    /// The non-zero terms as `(exponents, coefficient)`, in increasing
+    /// lexicographic order of the exponent vectors.
+    pub fn terms(&self) -> impl Iterator<Item = (&Vec<u128>, &FieldElement)> {
+        self.terms.iter()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Largest sum of exponents over all terms, or -1 for the zero
+    /// polynomial.
+    pub fn total_degree(&self) -> i128 {
+        self.terms
+            .keys()
+            .map(|k| k.iter().sum::<u128>() as i128)
+            .max()
+            .unwrap_or(-1)
+    }
+
+    /// Largest exponent of variable `var`, or -1 for the zero polynomial. A
+    /// variable beyond the arity doesn't appear, so its degree is 0.
+    pub fn degree_in(&self, var: usize) -> i128 {
+        self.terms
+            .keys()
+            .map(|k| k.get(var).map_or(0, |e| *e as i128))
+            .max()
+            .unwrap_or(-1)
+    }
+
+    /// Raises the polynomial to `exponent`. Monomials are raised directly and
+    /// sums of a few terms to small powers are expanded with the multinomial
+    /// theorem, anything else goes through square-and-multiply. The zero
+    /// polynomial carries no field to build a one from, so it stays zero.
+    pub fn pow(&self, exponent: u128) -> Self {
+        let Some(field) = self.terms.values().next().map(|c| c.field) else {
+            return self.clone();
+        };
+        let num_variables = self.num_variables;
+
+        if exponent == 0 {
+            return MPolynomial::constant(num_variables, field.one());
+        }
+
+        let terms: Vec<(&Vec<u128>, &FieldElement)> = self.terms.iter().collect();
+        let expansion = binomial(exponent + terms.len() as u128 - 1, exponent);
+        if exponent <= MULTINOMIAL_MAX_EXPONENT && expansion <= MULTINOMIAL_EXPANSION_LIMIT {
+            let mut acc = Vec::new();
+            expand_multinomial(
+                &terms,
+                exponent,
+                vec![0; num_variables],
+                field.one(),
+                &mut acc,
+            );
+            return MPolynomial::with_arity(num_variables, acc);
+        }
+
+        let mut acc = MPolynomial::constant(num_variables, field.one());
+        for i in (0..u128::BITS - exponent.leading_zeros()).rev() {
+            acc = acc.clone() * acc;
+            if (exponent >> i) & 1 == 1 {
+                acc = acc * self.clone();
+            }
+        }
+
+        acc
+    }
+
+    /// Evaluates the polynomial at `point`, which needs a coordinate for
+    /// every variable.
+    pub fn evaluate(&self, point: &[FieldElement]) -> FieldElement {
+        assert!(
+            point.len() == self.num_variables,
+            "point needs a coordinate for every variable"
+        );
+        let Some(field) = point.first().map(|x| x.field) else {
+            return self.terms.values().copied().next().unwrap_or_default();
+        };
+
+        self.terms
+            .iter()
+            .fold(field.zero(), |acc, (exponents, coefficient)| {
+                acc + exponents
+                    .iter()
+                    .zip(point)
+                    .fold(*coefficient, |term, (e, x)| term * x.modexp(*e as usize))
+            })
+    }
+
+    /// Fixes the given variables to constants and leaves the others alone.
+    /// The arity doesn't change, so the remaining variables keep their
+    /// indices; the fixed ones simply no longer occur.
+    pub fn partial_evaluate(&self, assignments: &[(usize, FieldElement)]) -> Self {
+        assert!(
+            assignments.iter().all(|(var, _)| *var < self.num_variables),
+            "variable out of range"
+        );
+
+        let terms = self.terms.iter().map(|(exponents, coefficient)| {
+            let mut exponents = exponents.clone();
+            let mut coefficient = *coefficient;
+            for (var, value) in assignments {
+                coefficient *= value.modexp(exponents[*var] as usize);
+                exponents[*var] = 0;
+            }
+            (exponents, coefficient)
+        });
+
+        MPolynomial::with_arity(self.num_variables, terms)
+    }
+
+    /// Replaces variable `var` by `replacement`. Powers of the replacement
+    /// are computed once up to the largest exponent of `var`.
+    pub fn substitute(&self, var: usize, replacement: &MPolynomial) -> Self {
+        assert!(var < self.num_variables, "variable out of range");
+        let num_variables = self.num_variables.max(replacement.num_variables);
+        let Some(field) = self.terms.values().next().map(|c| c.field) else {
+            return MPolynomial::zero(num_variables);
+        };
+
+        let mut powers = vec![MPolynomial::constant(num_variables, field.one())];
+        for _ in 0..self.degree_in(var) {
+            powers.push(powers[powers.len() - 1].clone() * replacement.clone());
+        }
+
+        let mut terms = Vec::new();
+        for (exponents, coefficient) in self.terms.iter() {
+            let mut rest = exponents.clone();
+            rest.resize(num_variables, 0);
+            rest[var] = 0;
+
+            for (k, v) in powers[exponents[var] as usize].terms() {
+                let shifted = rest.iter().zip(k).map(|(a, b)| a + b).collect();
+                terms.push((shifted, *coefficient * *v));
+            }
+        }
+
+        MPolynomial::with_arity(num_variables, terms)
+    }
+
+    /// Moves variable `i` to `mapping[i]` in a polynomial of `num_variables`
+    /// variables. Variables mapped to the same index are merged.
+    pub fn rename_variables(&self, mapping: &[usize], num_variables: usize) -> Self {
+        assert!(
+            mapping.len() == self.num_variables,
+            "need a target for every variable"
+        );
+        assert!(
+            mapping.iter().all(|target| *target < num_variables),
+            "variable mapped out of range"
+        );
+
+        let terms = self.terms.iter().map(|(exponents, coefficient)| {
+            let mut renamed = vec![0; num_variables];
+            for (e, target) in exponents.iter().zip(mapping) {
+                renamed[*target] += e;
+            }
+            (renamed, *coefficient)
+        });
+
+        MPolynomial::with_arity(num_variables, terms)
+    }
 
-            acc
+    /// Reorders the variables, moving variable `i` to `permutation[i]`.
+    pub fn permute_variables(&self, permutation: &[usize]) -> Self {
+        let mut seen = vec![false; permutation.len()];
+        for target in permutation {
+            assert!(
+                *target < seen.len() && !seen[*target],
+                "not a permutation of the variables"
+            );
+            seen[*target] = true;
         }
+
+        self.rename_variables(permutation, self.num_variables)
     }
 }
 
-impl MPolynomial {
-    pub fn new(self, dictionary: HashMap<Vec<u128>, FieldElement>) -> Self {
-        MPolynomial { dictionary }
+/// Collects every term of `(Σ c_i·m_i)^remaining` into `acc`, scaled by
+/// `coefficient` and shifted by `exponents`. The multinomial coefficient is
+/// built up one term at a time as a product of binomials.
+fn expand_multinomial(
+    terms: &[(&Vec<u128>, &FieldElement)],
+    remaining: u128,
+    exponents: Vec<u128>,
+    coefficient: FieldElement,
+    acc: &mut Vec<(Vec<u128>, FieldElement)>,
+) {
+    let field = coefficient.field;
+    let Some(((monomial, c), rest)) = terms.split_first() else {
+        acc.push((exponents, coefficient));
+        return;
+    };
+
+    // The last term takes whatever power is left.
+    let powers = if rest.is_empty() {
+        remaining..=remaining
+    } else {
+        0..=remaining
+    };
+    for k in powers {
+        let mut shifted = exponents.clone();
+        for (e, m) in shifted.iter_mut().zip(monomial.iter()) {
+            *e += k * m;
+        }
+        let ways = FieldElement::from_u64(binomial(remaining, k) as u64, field);
+        let scaled = coefficient * ways * c.modexp(k as usize);
+        expand_multinomial(rest, remaining - k, shifted, scaled, acc);
     }
+}
 
-    pub fn constant(&self, element: FieldElement) -> Self {
-        let mut dictionary = HashMap::new();
-        dictionary.insert(vec![0], element);
+/// `n` choose `k`, saturating at `u128::MAX`.
+fn binomial(n: u128, k: u128) -> u128 {
+    let k = k.min(n - k);
+    let mut acc: u128 = 1;
+    for i in 0..k {
+        // acc·(n - i) is divisible by i + 1 since acc = C(n, i).
+        acc = match acc.checked_mul(n - i) {
+            Some(product) => product / (i + 1),
+            None => return u128::MAX,
+        };
+    }
+
+    acc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{random::FieldRng, testing};
 
-        MPolynomial { dictionary }
+    const NUM_VARIABLES: usize = 3;
+
+    fn random(rng: &mut FieldRng) -> MPolynomial {
+        let num_terms = rng.below(6);
+        rng.mpolynomial(NUM_VARIABLES, num_terms, 4)
     }
 
-    pub fn is_zero(&self) -> bool {
-        if self.dictionary.is_empty() {
-            true
-        } else {
-            for v in self.dictionary.values() {
-                if v.is_zero() == false {
-                    return false
-                }
+    #[test]
+    fn evaluation_is_a_homomorphism() {
+        let mut rng = testing::rng(41);
+        for _ in 0..100 {
+            let f = random(&mut rng);
+            let g = random(&mut rng);
+            let x: Vec<FieldElement> = (0..NUM_VARIABLES)
+                .map(|_| testing::element(&mut rng))
+                .collect();
+            let (fx, gx) = (f.evaluate(&x), g.evaluate(&x));
+
+            assert_eq!((f.clone() + g.clone()).evaluate(&x), fx + gx);
+            assert_eq!((f.clone() - g.clone()).evaluate(&x), fx - gx);
+            assert_eq!((f.clone() * g.clone()).evaluate(&x), fx * gx);
+            assert_eq!((-f.clone()).evaluate(&x), -fx);
+            if !f.is_zero() {
+                let exponent = rng.below(6);
+                assert_eq!(f.pow(exponent as u128).evaluate(&x), fx.modexp(exponent));
             }
+        }
+    }
+
+    #[test]
+    fn partial_evaluation_and_substitution_commute_with_evaluation() {
+        let mut rng = testing::rng(43);
+        for _ in 0..100 {
+            let f = random(&mut rng);
+            let g = random(&mut rng);
+            let mut x: Vec<FieldElement> = (0..NUM_VARIABLES)
+                .map(|_| testing::element(&mut rng))
+                .collect();
+            let var = rng.below(NUM_VARIABLES);
+
+            let partial = f.partial_evaluate(&[(var, x[var])]);
+            assert_eq!(partial.degree_in(var).max(0), 0);
+            assert_eq!(partial.evaluate(&x), f.evaluate(&x));
 
-            true
+            let substituted = f.substitute(var, &g).evaluate(&x);
+            x[var] = g.evaluate(&x);
+            assert_eq!(substituted, f.evaluate(&x));
         }
     }
 
-    pub fn variables(&self, num_variables: u128, field: &Field) -> Vec<Self> {
-        todo!()
+    #[test]
+    fn pow_is_repeated_multiplication() {
+        let mut rng = testing::rng(47);
+        for _ in 0..20 {
+            let f = random(&mut rng);
+            let mut expected = match f.terms().next() {
+                Some((_, c)) => MPolynomial::constant(NUM_VARIABLES, c.field.one()),
+                None => MPolynomial::zero(NUM_VARIABLES),
+            };
+            for exponent in 0..5 {
+                assert_eq!(f.pow(exponent), expected);
+                expected = expected * f.clone();
+            }
+        }
+    }
+
+    #[test]
+    fn terms_are_canonical() {
+        let field = Field::new();
+        let mut rng = testing::rng(53);
+        let [a, b] = [rng.nonzero_element(), rng.nonzero_element()];
+
+        // Like terms are merged and vanishing ones dropped, whatever the
+        // order they come in.
+        let f = MPolynomial::new(vec![
+            (vec![1, 0, 2], a),
+            (vec![0, 1, 0], b),
+            (vec![1, 0, 2], -a),
+            (vec![2, 0, 0], field.zero()),
+            (vec![0, 1, 0], b),
+        ]);
+        let g = MPolynomial::new(vec![(vec![0, 1, 0], b + b)]);
+        assert_eq!(f.terms().count(), 1);
+        assert_eq!(f, MPolynomial::zero(NUM_VARIABLES) + g.clone());
+
+        let h = random(&mut rng);
+        assert_eq!(h.clone() - h.clone(), MPolynomial::zero(NUM_VARIABLES));
+        assert!((h.clone() - h).is_zero());
+        assert!(MPolynomial::new(vec![(vec![3, 1], field.zero())]).is_zero());
+
+        assert_eq!(f.degree_in(0), 0);
+        assert_eq!(f.degree_in(1), 1);
+        assert_eq!(f.degree_in(NUM_VARIABLES + 4), 0);
+        assert_eq!(MPolynomial::zero(NUM_VARIABLES).degree_in(7), -1);
     }
 }
@@ -0,0 +1,1812 @@
+use std::io;
+
+use crate::{
+    air::DegreeAnalysis,
+    algebra::{Field, FieldElement},
+    constraint::Expression,
+    domain::Domain,
+    evaluation::EvaluationForm,
+    hash::{Digest, Hasher},
+    merkle::{leaf_bytes, MerkleProof, MerkleRootBuilder, MerkleTree},
+    multipolynomial::MPolynomial,
+    parallel::*,
+    periodic::PeriodicColumn,
+    preprocessed::PreprocessedColumns,
+    proof_stream::{
+        sample_indices, sample_weights, ProofObject, ProofStream, INDEX_BYTES, SAMPLE_BYTES,
+    },
+    random::FieldRng,
+    storage::{ColumnStore, MemoryReport, Storage},
+    unipolynomial::UPolynomial,
+    vanishing::Vanishing,
+    zk::{randomize_trace, sample_randomizers, CompositionMask},
+};
+
+/// One table of a multi-table AIR, such as the processor, memory or
+/// instruction table of a VM.
+///
+/// Base columns hold the witness. Extension columns are computed row by row
+/// from the padded base columns and the challenges sampled once every base
+/// table is committed, typically running sums or products tying the table to
+/// the others. Constraints refer to columns by name: base columns, then
+/// extension, preprocessed and periodic columns.
+pub trait Table {
+    fn name(&self) -> &str;
+
+    fn base_columns(&self) -> Vec<String>;
+
+    fn extension_columns(&self) -> Vec<String>;
+
+    /// Fixed columns that don't depend on the witness, such as a program.
+    /// The prover gets their values as `PreprocessedColumns`, and the
+    /// verifier only knows them by the root in
+    /// `MultiTableStark::preprocessed_roots`.
+    fn preprocessed_columns(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Columns repeating with a period dividing the height, such as round
+    /// constants. Neither side commits to them, the verifier evaluates them
+    /// itself.
+    fn periodic_columns(&self) -> Vec<PeriodicColumn> {
+        Vec::new()
+    }
+
+    /// Row appended after `last_row` to pad the base columns to a power-of-two
+    /// height.
+    fn padding_row(&self, last_row: &[FieldElement]) -> Vec<FieldElement>;
+
+    /// Extension columns of the row with base columns `base_row`, given the
+    /// whole previous row, base columns first, or `None` in the first row.
+    /// Rows are extended top to bottom, one at a time, so that the prover
+    /// never has to hold a table in memory.
+    fn extension_row(
+        &self,
+        base_row: &[FieldElement],
+        previous: Option<&[FieldElement]>,
+        challenges: &[FieldElement],
+    ) -> Vec<FieldElement>;
+
+    /// Constraints on the first row, e.g. the initial value of a running sum.
+    fn first_row_constraints(&self, challenges: &[FieldElement]) -> Vec<Expression>;
+
+    fn transition_constraints(&self, challenges: &[FieldElement]) -> Vec<Expression>;
+}
+
+/// An extension column of one of the tables, whose value in the last row is
+/// sent along with the proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Terminal {
+    pub table: usize,
+    pub column: String,
+}
+
+/// Proves several tables of different heights together: commit to every base
+/// table, sample the cross-table challenges, commit to every extension table
+/// and send its terminals, then fold the quotients of all the AIRs into one
+/// codeword for a single FRI. Every table is committed over the whole
+/// evaluation domain, and opened wherever the combined codeword is queried so
+/// the verifier can recompute it there.
+#[derive(Debug, Clone)]
+pub struct MultiTableStark {
+    pub expansion_factor: usize,
+    pub num_challenges: usize,
+    /// Tallest padded table a proof may declare, which bounds the domain the
+    /// verifier has to work over.
+    pub max_height: usize,
+    /// Points of the evaluation domain at which the combined codeword is
+    /// checked against the tables.
+    pub num_queries: usize,
+    /// Hide the witness: every column takes random values at two points
+    /// outside the domains per query, since a query opens it at two points,
+    /// and the combined codeword is masked with a committed random codeword.
+    /// Every Merkle leaf is salted, and opened along with its salt.
+    pub zero_knowledge: bool,
+    /// Pairs of terminals the verifier checks for equality, such as the
+    /// running products of both sides of a permutation argument.
+    pub terminal_equalities: Vec<(Terminal, Terminal)>,
+    /// Per table, the root of its preprocessed columns committed with
+    /// `PreprocessedColumns::commit` over its padded height and the
+    /// evaluation domain of the proof. Tables without preprocessed columns
+    /// may be left out at the end.
+    pub preprocessed_roots: Vec<Option<Digest>>,
+}
+
+/// The codeword the prover runs FRI on, over the largest evaluation domain.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct CombinedCodeword {
+    pub domain: Domain,
+    pub codeword: Vec<FieldElement>,
+    pub degree_bound: usize,
+}
+
+/// The combined codeword of `prove_streaming`, kept in the prover's storage,
+/// and the memory the prover used to get there. Its stored bytes add up the
+/// base columns and every store the prover created along the way.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct StreamedCodeword {
+    pub domain: Domain,
+    pub codeword: ColumnStore,
+    pub degree_bound: usize,
+    pub memory: MemoryReport,
+}
+
+/// Everything the verifier read and checked, down to the combined codeword
+/// agreeing with the tables at the queried points. FRI still has to show
+/// that the codeword committed to by `combined_root` is of degree at most
+/// `degree_bound`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiTableClaim {
+    pub heights: Vec<usize>,
+    pub base_roots: Vec<Digest>,
+    pub extension_roots: Vec<Digest>,
+    pub challenges: Vec<FieldElement>,
+    pub terminals: Vec<Vec<FieldElement>>,
+    /// Commitment to the mask of the combined codeword in a zero-knowledge
+    /// proof.
+    pub mask_root: Option<Digest>,
+    pub combined_root: Digest,
+    pub degree_bound: usize,
+}
+
+/// Constraints of a table lowered for fixed challenges, with the degree of
+/// every quotient: first row constraints, transition constraints, then one
+/// terminal constraint per extension column. The rows the constraints are
+/// evaluated on hold base, extension and preprocessed columns, followed by
+/// the values of `periodic` at the row's point.
+struct LoweredTable {
+    height: usize,
+    num_base: usize,
+    periodic: Vec<PeriodicColumn>,
+    first_row: Vec<MPolynomial>,
+    transition: Vec<MPolynomial>,
+    quotient_degrees: Vec<usize>,
+}
+
+#[allow(dead_code)]
+impl MultiTableStark {
+    /// Runs the commitment rounds over `base_tables`, given as the columns of
+    /// every table in the order of `tables`, and the committed `preprocessed`
+    /// columns of the tables that have any, in the same order. The
+    /// zero-knowledge randomness is seeded from the operating system.
+    pub fn prove<H: Hasher>(
+        &self,
+        tables: &[&dyn Table],
+        base_tables: &[Vec<Vec<FieldElement>>],
+        preprocessed: &[Option<&PreprocessedColumns<H>>],
+        proof_stream: &mut ProofStream<H>,
+    ) -> CombinedCodeword {
+        let field = field_of(base_tables);
+        let base_tables: Vec<ColumnStore> = base_tables
+            .iter()
+            .map(|columns| store_columns(columns, &Storage::Memory, field))
+            .collect::<io::Result<_>>()
+            .expect("storing columns in memory can't fail");
+        let mut rng = FieldRng::from_entropy(field);
+        let streamed = self
+            .prove_streaming(
+                tables,
+                &base_tables,
+                preprocessed,
+                &Storage::Memory,
+                usize::MAX,
+                &mut rng,
+                proof_stream,
+            )
+            .expect("storing codewords in memory can't fail");
+
+        CombinedCodeword {
+            domain: streamed.domain,
+            codeword: streamed
+                .codeword
+                .read(0, 0, streamed.domain.size())
+                .expect("storing codewords in memory can't fail"),
+            degree_bound: streamed.degree_bound,
+        }
+    }
+
+    /// Same as `prove`, and sends the same proof, for traces too long to hold
+    /// every codeword as `FieldElement`s. The base columns of every table come
+    /// in a column store of their own, holding the unpadded rows, which is
+    /// only ever read `window` rows at a time. Padded tables, extension
+    /// columns and low degree extensions all go into compact column stores
+    /// kept in `storage`. Only one column of one table's height is ever held as
+    /// `FieldElement`s, while it gets extended one coset at a time. Merkle
+    /// leaves, quotients and the combined codeword are computed `window` rows
+    /// at a time. Randomizers and the mask of a zero-knowledge proof are drawn
+    /// from `rng`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prove_streaming<H: Hasher>(
+        &self,
+        tables: &[&dyn Table],
+        base_tables: &[ColumnStore],
+        preprocessed: &[Option<&PreprocessedColumns<H>>],
+        storage: &Storage,
+        window: usize,
+        rng: &mut FieldRng,
+        proof_stream: &mut ProofStream<H>,
+    ) -> io::Result<StreamedCodeword> {
+        assert!(
+            tables.len() == base_tables.len() && !tables.is_empty(),
+            "need the base columns of every table"
+        );
+        assert!(window > 0, "window has to hold at least one row");
+        let field = base_tables[0].field();
+        let mut stored_bytes: usize = base_tables.iter().map(ColumnStore::resident_bytes).sum();
+
+        let mut traces: Vec<ColumnStore> = Vec::with_capacity(tables.len());
+        for (table, columns) in tables.iter().zip(base_tables) {
+            traces.push(pad(*table, columns, storage, window)?);
+        }
+        let heights: Vec<usize> = traces.iter().map(ColumnStore::len).collect();
+        assert!(
+            heights.iter().all(|h| *h <= self.max_height),
+            "table taller than the maximum height"
+        );
+        proof_stream.push(ProofObject::Heights(
+            heights.iter().map(|h| *h as u64).collect(),
+        ));
+
+        let domain = self
+            .evaluation_domain(&heights, field)
+            .expect("field has no subgroup that large");
+        let window = window.min(domain.size());
+        let preprocessed: Vec<Option<&PreprocessedColumns<H>>> = tables
+            .iter()
+            .enumerate()
+            .map(|(i, table)| self.preprocessed_of(*table, i, preprocessed, &domain))
+            .collect();
+        let randomizer_points = self.randomizer_points(&domain);
+        let mut base_codewords: Vec<Codewords> = Vec::with_capacity(tables.len());
+        for trace in &traces {
+            let randomizers = sample_randomizers(rng, trace.num_columns(), randomizer_points.len());
+            let codewords = Codewords {
+                salt_seed: self.salt_seed(rng),
+                ..low_degree_extend(trace, &domain, &randomizer_points, &randomizers, storage)?
+            };
+            let root = commit::<H>(&codewords, window)?;
+            proof_stream.push(ProofObject::MerkleRoot(root));
+            stored_bytes += trace.resident_bytes() + codewords.store.resident_bytes();
+            base_codewords.push(codewords);
+        }
+
+        let challenges = self.sample_challenges(
+            &proof_stream.prover_fiat_shamir(self.num_challenges * SAMPLE_BYTES),
+            field,
+        );
+
+        let mut extension_codewords: Vec<Codewords> = Vec::with_capacity(tables.len());
+        let mut terminals: Vec<Vec<FieldElement>> = Vec::with_capacity(tables.len());
+        for (table, trace) in tables.iter().zip(traces) {
+            let extension = extend(*table, &trace, &challenges, storage, window)?;
+            let randomizers =
+                sample_randomizers(rng, extension.num_columns(), randomizer_points.len());
+            let codewords = Codewords {
+                salt_seed: self.salt_seed(rng),
+                ..low_degree_extend(
+                    &extension,
+                    &domain,
+                    &randomizer_points,
+                    &randomizers,
+                    storage,
+                )?
+            };
+            let root = commit::<H>(&codewords, window)?;
+            proof_stream.push(ProofObject::MerkleRoot(root));
+
+            terminals.push(extension.rows(extension.len() - 1, 1)?.remove(0));
+            stored_bytes += extension.resident_bytes() + codewords.store.resident_bytes();
+            extension_codewords.push(codewords);
+        }
+        for table_terminals in &terminals {
+            proof_stream.push(ProofObject::FieldElements(table_terminals.clone()));
+        }
+
+        let lowered: Vec<LoweredTable> = tables
+            .iter()
+            .zip(&heights)
+            .map(|(table, height)| self.lower(*table, *height, &challenges, field))
+            .collect();
+        let degree_bound = degree_bound(&lowered);
+        assert!(
+            degree_bound < domain.size(),
+            "expansion factor too small for the degree of the constraints"
+        );
+
+        let mask = if self.zero_knowledge {
+            let mask = CompositionMask::sample(rng, degree_bound + 1);
+            let height = heights.iter().max().unwrap();
+            let mut store = ColumnStore::new(storage, field, domain.size());
+            extend_polynomial(mask.polynomial(), &domain, *height, &mut store)?;
+            let codewords = Codewords {
+                store,
+                height: *height,
+                salt_seed: self.salt_seed(rng),
+            };
+            let root = commit::<H>(&codewords, window)?;
+            proof_stream.push(ProofObject::MerkleRoot(root));
+            stored_bytes += codewords.store.resident_bytes();
+            Some(codewords)
+        } else {
+            None
+        };
+
+        let num_quotients: usize = lowered.iter().map(|l| l.quotient_degrees.len()).sum();
+        let weights = sample_weights(
+            &proof_stream.prover_fiat_shamir(2 * num_quotients * SAMPLE_BYTES),
+            2 * num_quotients,
+            field,
+        );
+
+        let size = domain.size();
+        let mut combined = Codewords {
+            store: ColumnStore::new(storage, field, size),
+            height: size,
+            salt_seed: self.salt_seed(rng),
+        };
+        combined.store.add_column()?;
+        let mut combined_root = MerkleRootBuilder::<H>::new();
+        let mut salts = combined.salts();
+        for start in (0..size).step_by(window) {
+            let count = window.min(size - start);
+            let points = window_points(&domain, start, count);
+
+            let mut current = Vec::with_capacity(tables.len());
+            let mut next = Vec::with_capacity(tables.len());
+            for (i, height) in heights.iter().enumerate() {
+                let rows = |start| {
+                    table_rows(
+                        &base_codewords[i],
+                        &extension_codewords[i],
+                        preprocessed[i],
+                        start,
+                        count,
+                    )
+                };
+                current.push(rows(start)?);
+                next.push(rows(start + size / height)?);
+            }
+            let mut values = combine(&lowered, &terminals, &weights, &current, &next, &points);
+            if let Some(mask) = &mask {
+                for (value, row) in values.iter_mut().zip(mask.rows(start, count)?) {
+                    *value += row[0];
+                }
+            }
+
+            combined.store.write(0, start, &values)?;
+            let leaves: Vec<Vec<u8>> = values.iter().map(|c| leaf_bytes(&[*c])).collect();
+            push_leaves(&mut combined_root, &leaves, salts.as_mut());
+        }
+        proof_stream.push(ProofObject::MerkleRoot(combined_root.root()));
+
+        let indices = sample_indices(
+            &proof_stream.prover_fiat_shamir(self.num_queries * INDEX_BYTES),
+            self.num_queries,
+            size,
+        );
+        for (i, height) in heights.iter().enumerate() {
+            let positions = with_next_rows(&indices, size / height, size);
+            for codewords in [&base_codewords[i], &extension_codewords[i]] {
+                open::<H>(codewords, &positions, window, proof_stream)?;
+            }
+            if let Some(columns) = preprocessed[i] {
+                columns.open(proof_stream, &positions);
+            }
+        }
+        if let Some(mask) = &mask {
+            open::<H>(mask, &indices, window, proof_stream)?;
+        }
+        open::<H>(&combined, &indices, window, proof_stream)?;
+
+        stored_bytes += combined.store.resident_bytes();
+
+        Ok(StreamedCodeword {
+            domain,
+            codeword: combined.store,
+            degree_bound,
+            memory: MemoryReport::new(stored_bytes),
+        })
+    }
+
+    /// Replays the commitment rounds, checks the terminal equalities, and
+    /// recomputes the combined codeword at the queried points from the opened
+    /// table rows. Returns `None` if the proof is malformed or a check fails.
+    pub fn verify<H: Hasher>(
+        &self,
+        tables: &[&dyn Table],
+        field: Field,
+        proof_stream: &mut ProofStream<H>,
+    ) -> Option<MultiTableClaim> {
+        let heights: Vec<usize> = proof_stream
+            .pull()?
+            .into_heights()?
+            .into_iter()
+            .map(|h| usize::try_from(h).ok())
+            .collect::<Option<_>>()?;
+        if heights.len() != tables.len()
+            || heights
+                .iter()
+                .any(|h| !h.is_power_of_two() || *h > self.max_height)
+        {
+            return None;
+        }
+        let domain = self.evaluation_domain(&heights, field)?;
+
+        let mut base_roots = Vec::with_capacity(tables.len());
+        for _ in tables {
+            base_roots.push(proof_stream.pull()?.into_merkle_root()?);
+        }
+        let challenges = self.sample_challenges(
+            &proof_stream.verifier_fiat_shamir(self.num_challenges * SAMPLE_BYTES),
+            field,
+        );
+
+        let mut extension_roots = Vec::with_capacity(tables.len());
+        for _ in tables {
+            extension_roots.push(proof_stream.pull()?.into_merkle_root()?);
+        }
+        let mut terminals = Vec::with_capacity(tables.len());
+        for table in tables {
+            let table_terminals = proof_stream.pull()?.into_field_elements()?;
+            if table_terminals.len() != table.extension_columns().len() {
+                return None;
+            }
+            terminals.push(table_terminals);
+        }
+
+        for (left, right) in &self.terminal_equalities {
+            if terminal(tables, &terminals, left)? != terminal(tables, &terminals, right)? {
+                return None;
+            }
+        }
+        if tables.iter().zip(&heights).any(|(table, height)| {
+            table
+                .periodic_columns()
+                .iter()
+                .any(|column| column.period() > *height)
+        }) {
+            return None;
+        }
+
+        let lowered: Vec<LoweredTable> = tables
+            .iter()
+            .zip(&heights)
+            .map(|(table, height)| self.lower(*table, *height, &challenges, field))
+            .collect();
+        let degree_bound = degree_bound(&lowered);
+        if degree_bound >= domain.size() {
+            return None;
+        }
+        let mask_root = if self.zero_knowledge {
+            Some(proof_stream.pull()?.into_merkle_root()?)
+        } else {
+            None
+        };
+        let num_quotients: usize = lowered.iter().map(|l| l.quotient_degrees.len()).sum();
+        let weights = sample_weights(
+            &proof_stream.verifier_fiat_shamir(2 * num_quotients * SAMPLE_BYTES),
+            2 * num_quotients,
+            field,
+        );
+        let combined_root = proof_stream.pull()?.into_merkle_root()?;
+
+        let size = domain.size();
+        let indices = sample_indices(
+            &proof_stream.verifier_fiat_shamir(self.num_queries * INDEX_BYTES),
+            self.num_queries,
+            size,
+        );
+        let mut current = Vec::with_capacity(tables.len());
+        let mut next = Vec::with_capacity(tables.len());
+        for (i, table) in tables.iter().enumerate() {
+            let positions = with_next_rows(&indices, size / heights[i], size);
+            let base = self.verify_opening(
+                proof_stream,
+                &base_roots[i],
+                table.base_columns().len(),
+                size,
+                &positions,
+            )?;
+            let extension = self.verify_opening(
+                proof_stream,
+                &extension_roots[i],
+                table.extension_columns().len(),
+                size,
+                &positions,
+            )?;
+            let mut rows: Vec<Vec<FieldElement>> = base
+                .into_iter()
+                .zip(extension)
+                .map(|(mut row, extension)| {
+                    row.extend(extension);
+                    row
+                })
+                .collect();
+            let num_preprocessed = table.preprocessed_columns().len();
+            if num_preprocessed > 0 {
+                let root = self.preprocessed_roots.get(i).copied().flatten()?;
+                let fixed = PreprocessedColumns::<H>::verify(
+                    proof_stream,
+                    &root,
+                    num_preprocessed,
+                    &domain,
+                    &positions,
+                )?;
+                for (row, fixed) in rows.iter_mut().zip(fixed) {
+                    row.extend(fixed);
+                }
+            }
+            next.push(rows.split_off(indices.len()));
+            current.push(rows);
+        }
+        let mut expected = combine(
+            &lowered,
+            &terminals,
+            &weights,
+            &current,
+            &next,
+            &indices
+                .iter()
+                .map(|i| domain.element(*i))
+                .collect::<Vec<_>>(),
+        );
+        if let Some(mask_root) = &mask_root {
+            let mask = self.verify_opening(proof_stream, mask_root, 1, size, &indices)?;
+            for (value, row) in expected.iter_mut().zip(mask) {
+                *value += row[0];
+            }
+        }
+        let values: Vec<FieldElement> = self
+            .verify_opening(proof_stream, &combined_root, 1, size, &indices)?
+            .into_iter()
+            .flatten()
+            .collect();
+        if expected != values {
+            return None;
+        }
+
+        Some(MultiTableClaim {
+            heights,
+            base_roots,
+            extension_roots,
+            challenges,
+            terminals,
+            mask_root,
+            combined_root,
+            degree_bound,
+        })
+    }
+
+    /// The largest table's evaluation domain, which contains the evaluation
+    /// domain of every smaller table, or `None` if the field has no subgroup
+    /// that large.
+    fn evaluation_domain(&self, heights: &[usize], field: Field) -> Option<Domain> {
+        let size = heights.iter().max()?.checked_mul(self.expansion_factor)?;
+        Domain::coset(size, field.generator()).ok()
+    }
+
+    fn sample_challenges(&self, bytes: &[u8], field: Field) -> Vec<FieldElement> {
+        sample_weights(bytes, self.num_challenges, field)
+    }
+
+    /// Where the columns of a zero-knowledge proof take their random values:
+    /// the first points of the coset of the evaluation domain's subgroup next
+    /// to it, which avoids the evaluation domain and every trace domain.
+    fn randomizer_points(&self, domain: &Domain) -> Vec<FieldElement> {
+        if !self.zero_knowledge {
+            return Vec::new();
+        }
+        let count = 2 * self.num_queries;
+        assert!(
+            count <= domain.size(),
+            "evaluation domain too small for the randomizers"
+        );
+        let offset = domain.field().generator();
+
+        (0..count).map(|i| offset * domain.element(i)).collect()
+    }
+
+    /// The preprocessed columns of table `index`, if it has any, checked
+    /// against the table, the evaluation domain and their root.
+    fn preprocessed_of<'a, H: Hasher>(
+        &self,
+        table: &dyn Table,
+        index: usize,
+        preprocessed: &[Option<&'a PreprocessedColumns<H>>],
+        domain: &Domain,
+    ) -> Option<&'a PreprocessedColumns<H>> {
+        let names = table.preprocessed_columns();
+        if names.is_empty() {
+            return None;
+        }
+        let columns = preprocessed
+            .get(index)
+            .copied()
+            .flatten()
+            .unwrap_or_else(|| panic!("table {} needs its preprocessed columns", table.name()));
+        assert!(
+            columns.names() == names.as_slice() && columns.codewords()[0].len() == domain.size(),
+            "preprocessed columns of table {} have the wrong shape",
+            table.name()
+        );
+        assert!(
+            self.preprocessed_roots.get(index).copied().flatten() == Some(columns.root()),
+            "preprocessed columns of table {} don't match their root",
+            table.name()
+        );
+
+        Some(columns)
+    }
+
+    /// Seed of the salts of a Merkle commitment, drawn from `rng` in a
+    /// zero-knowledge proof, so that unopened leaves can't be recovered from
+    /// their hashes.
+    fn salt_seed(&self, rng: &mut FieldRng) -> Option<Digest> {
+        self.zero_knowledge.then(|| rng.digest())
+    }
+
+    /// Reads the rows sent by `open` and checks them against `root`, or
+    /// returns `None` if they don't belong to the committed rows. The leaves
+    /// of a zero-knowledge proof come with their salts.
+    fn verify_opening<H: Hasher>(
+        &self,
+        proof_stream: &mut ProofStream<H>,
+        root: &Digest,
+        num_columns: usize,
+        num_rows: usize,
+        positions: &[usize],
+    ) -> Option<Vec<Vec<FieldElement>>> {
+        let mut rows = Vec::with_capacity(positions.len());
+        for _ in positions {
+            let row = proof_stream.pull()?.into_field_elements()?;
+            if row.len() != num_columns {
+                return None;
+            }
+            rows.push(row);
+        }
+        let salts = if self.zero_knowledge {
+            Some(proof_stream.pull()?.into_salts()?)
+        } else {
+            None
+        };
+        let proof: MerkleProof = proof_stream.pull()?.into_merkle_proof()?;
+
+        let leaves: Vec<Vec<u8>> = rows.iter().map(|row| leaf_bytes(row)).collect();
+        MerkleTree::<H>::verify_many(root, num_rows, positions, &leaves, salts.as_deref(), &proof)
+            .then_some(rows)
+    }
+
+    /// Lowers the constraints of a table and bounds the degrees of its
+    /// quotients, given that every column is interpolated from its `height`
+    /// rows and the randomizer points.
+    fn lower(
+        &self,
+        table: &dyn Table,
+        height: usize,
+        challenges: &[FieldElement],
+        field: Field,
+    ) -> LoweredTable {
+        let num_randomizers = if self.zero_knowledge {
+            2 * self.num_queries
+        } else {
+            0
+        };
+        lower(
+            table,
+            height,
+            height - 1 + num_randomizers,
+            challenges,
+            field,
+        )
+    }
+}
+
+/// Commits to the rows of a table over the whole evaluation domain, so that
+/// every point and the point one trace step further can be opened.
+fn commit<H: Hasher>(codewords: &Codewords, window: usize) -> io::Result<Digest> {
+    let mut root = MerkleRootBuilder::<H>::new();
+    feed(&mut root, codewords, window)?;
+
+    Ok(root.root())
+}
+
+/// Sends the rows at `positions`, their salts if the commitment is salted,
+/// and one batched authentication path for them, recomputing the tree from
+/// the stored rows instead of keeping it.
+fn open<H: Hasher>(
+    codewords: &Codewords,
+    positions: &[usize],
+    window: usize,
+    proof_stream: &mut ProofStream<H>,
+) -> io::Result<()> {
+    for position in positions {
+        let row = codewords.rows(*position, 1)?.remove(0);
+        proof_stream.push(ProofObject::FieldElements(row));
+    }
+    let mut tree = MerkleRootBuilder::<H>::with_openings(codewords.len(), positions);
+    feed(&mut tree, codewords, window)?;
+    if let Some(salts) = tree.salts() {
+        proof_stream.push(ProofObject::Salts(salts));
+    }
+    proof_stream.push(ProofObject::MerkleProof(tree.proof()));
+
+    Ok(())
+}
+
+/// Feeds every row of `codewords` to `tree`, `window` rows at a time.
+fn feed<H: Hasher>(
+    tree: &mut MerkleRootBuilder<H>,
+    codewords: &Codewords,
+    window: usize,
+) -> io::Result<()> {
+    let mut salts = codewords.salts();
+    for start in (0..codewords.len()).step_by(window) {
+        let count = window.min(codewords.len() - start);
+        let leaves: Vec<Vec<u8>> = codewords
+            .rows(start, count)?
+            .iter()
+            .map(|row| leaf_bytes(row))
+            .collect();
+        push_leaves(tree, &leaves, salts.as_mut());
+    }
+
+    Ok(())
+}
+
+/// Pushes `leaves` to `tree`, each salted with the next salt of `salts` if
+/// the commitment is salted.
+fn push_leaves<H: Hasher>(
+    tree: &mut MerkleRootBuilder<H>,
+    leaves: &[Vec<u8>],
+    salts: Option<&mut FieldRng>,
+) {
+    match salts {
+        Some(rng) => {
+            let salts: Vec<Digest> = leaves.iter().map(|_| rng.digest()).collect();
+            tree.push_salted(leaves, &salts);
+        }
+        None => tree.push(leaves),
+    }
+}
+
+/// The queried `indices` followed by the point one trace step of `step`
+/// further for each of them.
+fn with_next_rows(indices: &[usize], step: usize, size: usize) -> Vec<usize> {
+    indices
+        .iter()
+        .copied()
+        .chain(indices.iter().map(|i| (i + step) % size))
+        .collect()
+}
+
+/// The columns of a table as a column store kept in `storage`.
+fn store_columns(
+    columns: &[Vec<FieldElement>],
+    storage: &Storage,
+    field: Field,
+) -> io::Result<ColumnStore> {
+    let mut store = ColumnStore::new(storage, field, columns.first().map_or(0, Vec::len));
+    for column in columns {
+        store.push(column)?;
+    }
+
+    Ok(store)
+}
+
+fn field_of(base_tables: &[Vec<Vec<FieldElement>>]) -> Field {
+    base_tables
+        .iter()
+        .flatten()
+        .flatten()
+        .next()
+        .expect("tables can't be empty")
+        .field
+}
+
+/// Copies the rows of `columns` and appends the table's padding rows up to a
+/// power of two, `window` rows at a time.
+fn pad(
+    table: &dyn Table,
+    columns: &ColumnStore,
+    storage: &Storage,
+    window: usize,
+) -> io::Result<ColumnStore> {
+    assert!(
+        columns.num_columns() == table.base_columns().len() && !columns.is_empty(),
+        "table {} has the wrong number of columns or no rows",
+        table.name()
+    );
+    let length = columns.len();
+    let height = length.next_power_of_two();
+    let mut padded = ColumnStore::new(storage, columns.field(), height);
+    for _ in 0..columns.num_columns() {
+        padded.add_column()?;
+    }
+    for start in (0..length).step_by(window) {
+        let count = window.min(length - start);
+        write_rows(&mut padded, start, &columns.rows(start, count)?)?;
+    }
+
+    let mut last: Vec<FieldElement> = columns.rows(length - 1, 1)?.remove(0);
+    for start in (length..height).step_by(window) {
+        let count = window.min(height - start);
+        let mut rows = Vec::with_capacity(count);
+        for _ in 0..count {
+            last = table.padding_row(&last);
+            rows.push(last.clone());
+        }
+        write_rows(&mut padded, start, &rows)?;
+    }
+
+    Ok(padded)
+}
+
+/// Runs `Table::extension_row` down the padded base columns, `window` rows at
+/// a time.
+fn extend(
+    table: &dyn Table,
+    base: &ColumnStore,
+    challenges: &[FieldElement],
+    storage: &Storage,
+    window: usize,
+) -> io::Result<ColumnStore> {
+    let width = table.extension_columns().len();
+    let mut extension = ColumnStore::new(storage, base.field(), base.len());
+    for _ in 0..width {
+        extension.add_column()?;
+    }
+
+    let mut previous: Option<Vec<FieldElement>> = None;
+    for start in (0..base.len()).step_by(window) {
+        let count = window.min(base.len() - start);
+        let mut rows = Vec::with_capacity(count);
+        for base_row in base.rows(start, count)? {
+            let row = table.extension_row(&base_row, previous.as_deref(), challenges);
+            assert!(
+                row.len() == width,
+                "table {} extended to the wrong shape",
+                table.name()
+            );
+            previous = Some(base_row.into_iter().chain(row.iter().copied()).collect());
+            rows.push(row);
+        }
+        write_rows(&mut extension, start, &rows)?;
+    }
+
+    Ok(extension)
+}
+
+fn write_rows(store: &mut ColumnStore, start: usize, rows: &[Vec<FieldElement>]) -> io::Result<()> {
+    for column in 0..store.num_columns() {
+        let values: Vec<FieldElement> = rows.iter().map(|row| row[column]).collect();
+        store.write(column, start, &values)?;
+    }
+
+    Ok(())
+}
+
+/// Extends the columns of `trace` to `domain` one at a time, each of them
+/// interpolated through `randomizers[column]` at `randomizer_points` as well,
+/// so that no more than a column's worth of `FieldElement`s is ever held in
+/// memory.
+fn low_degree_extend(
+    trace: &ColumnStore,
+    domain: &Domain,
+    randomizer_points: &[FieldElement],
+    randomizers: &[Vec<FieldElement>],
+    storage: &Storage,
+) -> io::Result<Codewords> {
+    let height = trace.len();
+    let trace_domain = Domain::new(height, domain.field()).expect("field has no such subgroup");
+    let mut store = ColumnStore::new(storage, domain.field(), domain.size());
+    for (column, randomness) in randomizers.iter().enumerate() {
+        let values = trace.read(column, 0, height)?;
+        let polynomial = randomize_trace(
+            &[values],
+            &trace_domain,
+            randomizer_points,
+            std::slice::from_ref(randomness),
+        )
+        .remove(0);
+        extend_polynomial(&polynomial, domain, height, &mut store)?;
+    }
+
+    Ok(Codewords {
+        store,
+        height,
+        salt_seed: None,
+    })
+}
+
+/// Adds the codeword of `polynomial` over `domain` to `store`, one coset of
+/// the subgroup of order `height` at a time, in the layout of `Codewords`.
+fn extend_polynomial(
+    polynomial: &UPolynomial,
+    domain: &Domain,
+    height: usize,
+    store: &mut ColumnStore,
+) -> io::Result<()> {
+    let index = store.add_column()?;
+    for coset in 0..domain.size() / height {
+        let coset_domain =
+            Domain::coset(height, domain.element(coset)).expect("field has no such subgroup");
+        let values = EvaluationForm::from_polynomial(polynomial, coset_domain);
+        store.write(index, coset * height, values.values())?;
+    }
+
+    Ok(())
+}
+
+/// Columns over the largest evaluation domain, stored the way
+/// `low_degree_extend` computes them: one coset of the subgroup of order
+/// `height` after the other. With `k` such cosets, point `r + k t` of the
+/// domain is stored at position `r height + t`.
+///
+/// A salted commitment hashes every row with its own salt, drawn in the
+/// order of the domain from a `FieldRng` seeded with `salt_seed`, so the
+/// salts can be drawn again whenever the rows are.
+#[derive(Debug)]
+struct Codewords {
+    store: ColumnStore,
+    height: usize,
+    salt_seed: Option<Digest>,
+}
+
+impl Codewords {
+    fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    /// Source of the salts of the rows, starting at the first one.
+    fn salts(&self) -> Option<FieldRng> {
+        self.salt_seed
+            .map(|seed| FieldRng::new(seed, self.store.field()))
+    }
+
+    /// Rows at points `start..start + count` of the domain, wrapping around
+    /// its end. Each coset contributes every `k`th of them, read in one go.
+    fn rows(&self, start: usize, count: usize) -> io::Result<Vec<Vec<FieldElement>>> {
+        let size = self.len();
+        let cosets = size / self.height;
+        let mut rows = vec![Vec::new(); count];
+        for coset in 0..cosets {
+            let first = (coset + cosets - start % cosets) % cosets;
+            if first >= count {
+                continue;
+            }
+            let num_rows = (count - 1 - first) / cosets + 1;
+            let t = (start + first) % size / cosets;
+            let head = num_rows.min(self.height - t);
+
+            let offset = coset * self.height;
+            let coset_rows = self.store.rows(offset + t, head)?;
+            let wrapped = self.store.rows(offset, num_rows - head)?;
+            for (j, row) in coset_rows.into_iter().chain(wrapped).enumerate() {
+                rows[first + j * cosets] = row;
+            }
+        }
+
+        Ok(rows)
+    }
+}
+
+fn lower(
+    table: &dyn Table,
+    height: usize,
+    column_bound: usize,
+    challenges: &[FieldElement],
+    field: Field,
+) -> LoweredTable {
+    let trace: Vec<String> = table
+        .base_columns()
+        .into_iter()
+        .chain(table.extension_columns())
+        .collect();
+    let trace: Vec<&str> = trace.iter().map(|name| name.as_str()).collect();
+    let preprocessed = table.preprocessed_columns();
+    let preprocessed: Vec<&str> = preprocessed.iter().map(|name| name.as_str()).collect();
+    let periodic = table.periodic_columns();
+    let periodic_names: Vec<&str> = periodic.iter().map(PeriodicColumn::name).collect();
+    let lower_all = |expressions: Vec<Expression>| -> Vec<MPolynomial> {
+        expressions
+            .iter()
+            .map(|e| {
+                e.lower_with_inputs(&trace, &preprocessed, &periodic_names, &field)
+                    .unwrap_or_else(|error| panic!("table {}: {}", table.name(), error))
+            })
+            .collect()
+    };
+    let first_row = lower_all(table.first_row_constraints(challenges));
+    let transition = lower_all(table.transition_constraints(challenges));
+
+    // Preprocessed and periodic columns aren't randomized.
+    let column_bounds: Vec<usize> = trace
+        .iter()
+        .map(|_| column_bound)
+        .chain(preprocessed.iter().map(|_| height - 1))
+        .chain(periodic.iter().map(|column| column.degree_bound(height)))
+        .collect();
+    let first_row_degrees = DegreeAnalysis::new(height, &first_row, &column_bounds)
+        .constraint_degrees
+        .into_iter()
+        .map(|d| d.saturating_sub(1));
+    let transition_degrees = DegreeAnalysis::new(height, &transition, &column_bounds)
+        .quotient_degrees
+        .into_iter();
+    let terminal_degrees = table
+        .extension_columns()
+        .into_iter()
+        .map(|_| column_bound.saturating_sub(1));
+
+    LoweredTable {
+        height,
+        num_base: table.base_columns().len(),
+        periodic,
+        first_row,
+        transition,
+        quotient_degrees: first_row_degrees
+            .chain(transition_degrees)
+            .chain(terminal_degrees)
+            .collect(),
+    }
+}
+
+fn degree_bound(tables: &[LoweredTable]) -> usize {
+    tables
+        .iter()
+        .flat_map(|t| t.quotient_degrees.iter().copied())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Points `start..start + count` of `domain`.
+fn window_points(domain: &Domain, start: usize, count: usize) -> Vec<FieldElement> {
+    let root = domain.root();
+    let mut point = domain.element(start);
+    let mut points = Vec::with_capacity(count);
+    for _ in 0..count {
+        points.push(point);
+        point *= root;
+    }
+
+    points
+}
+
+/// Base, extension and preprocessed columns of rows `start..start + count`,
+/// wrapping around the end of the domain.
+fn table_rows<H: Hasher>(
+    base: &Codewords,
+    extension: &Codewords,
+    preprocessed: Option<&PreprocessedColumns<H>>,
+    start: usize,
+    count: usize,
+) -> io::Result<Vec<Vec<FieldElement>>> {
+    let size = base.len();
+    let base = base.rows(start, count)?;
+    let extension = extension.rows(start, count)?;
+
+    Ok(base
+        .into_iter()
+        .zip(extension)
+        .enumerate()
+        .map(|(j, (mut row, extension))| {
+            row.extend(extension);
+            if let Some(columns) = preprocessed {
+                row.extend(columns.row((start + j) % size));
+            }
+            row
+        })
+        .collect())
+}
+
+/// Values of the combined codeword at `points`, given the rows of every table
+/// at those points and one trace step further. Each quotient gets a pair of
+/// weights `(a, b)` and enters as `(a + b x^s) q(x)`, where the shift `s`
+/// lifts its degree to the common degree bound.
+fn combine(
+    tables: &[LoweredTable],
+    terminals: &[Vec<FieldElement>],
+    weights: &[FieldElement],
+    current: &[Vec<Vec<FieldElement>>],
+    next: &[Vec<Vec<FieldElement>>],
+    points: &[FieldElement],
+) -> Vec<FieldElement> {
+    let degree_bound = degree_bound(tables);
+    let mut values = vec![points[0].field.zero(); points.len()];
+    let mut weights = weights.chunks(2);
+    for (i, table) in tables.iter().enumerate() {
+        let quotients = quotient_codewords(table, &current[i], &next[i], &terminals[i], points);
+        for (quotient, degree) in quotients.iter().zip(&table.quotient_degrees) {
+            let weight = weights.next().unwrap();
+            let shift = degree_bound - degree;
+            for (j, q) in quotient.iter().enumerate() {
+                values[j] += (weight[0] + weight[1] * points[j].modexp(shift)) * *q;
+            }
+        }
+    }
+
+    values
+}
+
+/// Quotient codewords of a table at `points` of the largest evaluation domain,
+/// in the order of `LoweredTable::quotient_degrees`, given its rows there and
+/// one trace step further. The next row of a table of height `h` is `N/h`
+/// points further along a domain of size `N`.
+fn quotient_codewords(
+    table: &LoweredTable,
+    current: &[Vec<FieldElement>],
+    next: &[Vec<FieldElement>],
+    terminals: &[FieldElement],
+    points: &[FieldElement],
+) -> Vec<Vec<FieldElement>> {
+    let field = points[0].field;
+    let count = points.len();
+    let height = table.height;
+    let omega = Domain::new(height, field)
+        .expect("field has no such subgroup")
+        .root();
+
+    // The periodic columns at the point and one trace step further.
+    let periodic = |x: &FieldElement| -> Vec<FieldElement> {
+        table
+            .periodic
+            .iter()
+            .map(|column| column.evaluate(x, height))
+            .collect()
+    };
+    let rows: Vec<Vec<FieldElement>> = into_iter!(0..count)
+        .map(|j| {
+            let x = points[j];
+            [
+                current[j].as_slice(),
+                &periodic(&x),
+                &next[j],
+                &periodic(&(x * omega)),
+            ]
+            .concat()
+        })
+        .collect();
+
+    let first = Vanishing::subgroup(1, field);
+    let last = Vanishing::coset(1, omega.inverse());
+    let transition = Vanishing::transition(&omega, height);
+
+    let mut quotients = Vec::new();
+    for constraint in &table.first_row {
+        let values: Vec<FieldElement> = iter!(rows).map(|row| constraint.evaluate(row)).collect();
+        quotients.push(first.divide_codeword(&values, points));
+    }
+    for constraint in &table.transition {
+        let values: Vec<FieldElement> = iter!(rows).map(|row| constraint.evaluate(row)).collect();
+        quotients.push(transition.divide_codeword(&values, points));
+    }
+    for (k, terminal) in terminals.iter().enumerate() {
+        let values: Vec<FieldElement> = current
+            .iter()
+            .map(|row| row[table.num_base + k] - *terminal)
+            .collect();
+        quotients.push(last.divide_codeword(&values, points));
+    }
+
+    quotients
+}
+
+fn terminal(
+    tables: &[&dyn Table],
+    terminals: &[Vec<FieldElement>],
+    terminal: &Terminal,
+) -> Option<FieldElement> {
+    let column = tables
+        .get(terminal.table)?
+        .extension_columns()
+        .iter()
+        .position(|c| *c == terminal.column)?;
+
+    Some(terminals[terminal.table][column])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constraint::{col, constant},
+        hash::Sha3,
+        testing,
+    };
+
+    /// A column counting up by one, and the running product of `c - x` over
+    /// it for a challenge `c`.
+    struct Counter {
+        base: &'static str,
+        extension: &'static str,
+    }
+
+    const X: Counter = Counter {
+        base: "x",
+        extension: "px",
+    };
+    const Y: Counter = Counter {
+        base: "y",
+        extension: "py",
+    };
+
+    impl Table for Counter {
+        fn name(&self) -> &str {
+            self.base
+        }
+
+        fn base_columns(&self) -> Vec<String> {
+            vec![self.base.to_string()]
+        }
+
+        fn extension_columns(&self) -> Vec<String> {
+            vec![self.extension.to_string()]
+        }
+
+        fn padding_row(&self, last_row: &[FieldElement]) -> Vec<FieldElement> {
+            vec![last_row[0] + last_row[0].field.one()]
+        }
+
+        fn extension_row(
+            &self,
+            base_row: &[FieldElement],
+            previous: Option<&[FieldElement]>,
+            challenges: &[FieldElement],
+        ) -> Vec<FieldElement> {
+            let product = previous.map_or(challenges[0].field.one(), |row| row[1]);
+            vec![product * (challenges[0] - base_row[0])]
+        }
+
+        fn first_row_constraints(&self, challenges: &[FieldElement]) -> Vec<Expression> {
+            vec![col(self.extension) - (constant(challenges[0]) - col(self.base))]
+        }
+
+        fn transition_constraints(&self, challenges: &[FieldElement]) -> Vec<Expression> {
+            let one = challenges[0].field.one();
+            vec![
+                col(self.base).next() - col(self.base) - constant(one),
+                col(self.extension).next()
+                    - col(self.extension) * (constant(challenges[0]) - col(self.base).next()),
+            ]
+        }
+    }
+
+    /// `x` steps by the periodic column `k`, and `y` is `x` times the
+    /// preprocessed column `p`.
+    struct Schedule {
+        steps: [u64; 4],
+    }
+
+    impl Table for Schedule {
+        fn name(&self) -> &str {
+            "schedule"
+        }
+
+        fn base_columns(&self) -> Vec<String> {
+            vec!["x".to_string(), "y".to_string()]
+        }
+
+        fn extension_columns(&self) -> Vec<String> {
+            vec![]
+        }
+
+        fn preprocessed_columns(&self) -> Vec<String> {
+            vec!["p".to_string()]
+        }
+
+        fn periodic_columns(&self) -> Vec<PeriodicColumn> {
+            let field = Field::new();
+            let steps = self
+                .steps
+                .iter()
+                .map(|step| FieldElement::from_u64(*step, field))
+                .collect();
+            vec![PeriodicColumn::new("k", steps, field).unwrap()]
+        }
+
+        fn padding_row(&self, _: &[FieldElement]) -> Vec<FieldElement> {
+            unreachable!("the trace fills its height")
+        }
+
+        fn extension_row(
+            &self,
+            _: &[FieldElement],
+            _: Option<&[FieldElement]>,
+            _: &[FieldElement],
+        ) -> Vec<FieldElement> {
+            vec![]
+        }
+
+        fn first_row_constraints(&self, _: &[FieldElement]) -> Vec<Expression> {
+            vec![col("y") - col("x") * col("p")]
+        }
+
+        fn transition_constraints(&self, _: &[FieldElement]) -> Vec<Expression> {
+            vec![
+                col("x").next() - col("x") - col("k"),
+                col("y").next() - col("x").next() * col("p").next(),
+            ]
+        }
+    }
+
+    fn stark() -> MultiTableStark {
+        MultiTableStark {
+            expansion_factor: 8,
+            num_challenges: 1,
+            max_height: 1 << 10,
+            num_queries: 16,
+            zero_knowledge: false,
+            terminal_equalities: vec![],
+            preprocessed_roots: vec![],
+        }
+    }
+
+    /// A table of 13 rows padded to 16 next to one of 4 rows.
+    fn base_tables() -> Vec<Vec<Vec<FieldElement>>> {
+        let field = Field::new();
+        let counting = |range: std::ops::Range<u64>| -> Vec<Vec<FieldElement>> {
+            vec![range.map(|v| FieldElement::from_u64(v, field)).collect()]
+        };
+
+        vec![counting(3..16), counting(7..11)]
+    }
+
+    /// `base_tables` in column stores kept in `storage`.
+    fn base_stores(storage: &Storage) -> Vec<ColumnStore> {
+        base_tables()
+            .iter()
+            .map(|columns| store_columns(columns, storage, Field::new()).unwrap())
+            .collect()
+    }
+
+    fn prove() -> ProofStream<Sha3> {
+        let mut proof_stream = ProofStream::new();
+        stark().prove(&[&X, &Y], &base_tables(), &[], &mut proof_stream);
+
+        proof_stream
+    }
+
+    fn objects(proof_stream: &ProofStream<Sha3>) -> Vec<ProofObject> {
+        let mut proof_stream = proof_stream.clone();
+        std::iter::from_fn(|| proof_stream.pull()).collect()
+    }
+
+    fn verify(objects: Vec<ProofObject>) -> Option<MultiTableClaim> {
+        let mut proof_stream = ProofStream::<Sha3>::new();
+        for object in objects {
+            proof_stream.push(object);
+        }
+        stark().verify(&[&X, &Y], Field::new(), &mut proof_stream)
+    }
+
+    /// Replays the query phase of an honest proof with the tables opened
+    /// against `combined` instead of the codeword they give.
+    fn with_combined_codeword(
+        honest: &[ProofObject],
+        combined: &[FieldElement],
+    ) -> Vec<ProofObject> {
+        let field = Field::new();
+        let stark = stark();
+        let claim = verify(honest.to_vec()).unwrap();
+        let size = combined.len();
+        let domain = stark.evaluation_domain(&claim.heights, field).unwrap();
+
+        // Heights, four table roots and two sets of terminals.
+        let mut proof_stream = ProofStream::<Sha3>::new();
+        for object in &honest[..7] {
+            proof_stream.push(object.clone());
+        }
+        let mut combined_codeword = Codewords {
+            store: ColumnStore::new(&Storage::Memory, field, size),
+            height: size,
+            salt_seed: None,
+        };
+        combined_codeword.store.push(combined).unwrap();
+        let root = commit::<Sha3>(&combined_codeword, size).unwrap();
+        proof_stream.push(ProofObject::MerkleRoot(root));
+
+        let indices = sample_indices(
+            &proof_stream.prover_fiat_shamir(stark.num_queries * INDEX_BYTES),
+            stark.num_queries,
+            size,
+        );
+        for (table, columns) in [X, Y].iter().zip(base_stores(&Storage::Memory)) {
+            let base = pad(table, &columns, &Storage::Memory, size).unwrap();
+            let extension =
+                extend(table, &base, &claim.challenges, &Storage::Memory, size).unwrap();
+            let positions = with_next_rows(&indices, size / base.len(), size);
+            for trace in [base, extension] {
+                let randomizers = vec![vec![]; trace.num_columns()];
+                let codewords =
+                    low_degree_extend(&trace, &domain, &[], &randomizers, &Storage::Memory)
+                        .unwrap();
+                open::<Sha3>(&codewords, &positions, size, &mut proof_stream).unwrap();
+            }
+        }
+        open::<Sha3>(&combined_codeword, &indices, size, &mut proof_stream).unwrap();
+
+        objects(&proof_stream)
+    }
+
+    #[test]
+    fn honest_proofs_verify() {
+        let proof_stream = prove();
+        let claim = verify(objects(&proof_stream)).unwrap();
+        assert_eq!(claim.heights, vec![16, 4]);
+        assert_eq!(claim.degree_bound, 15);
+
+        let mut deserialized = ProofStream::<Sha3>::deserialize(&proof_stream.serialize()).unwrap();
+        assert_eq!(
+            stark().verify(&[&X, &Y], Field::new(), &mut deserialized),
+            Some(claim)
+        );
+    }
+
+    #[test]
+    fn tampered_proofs_are_rejected() {
+        let honest = objects(&prove());
+        let num_queries = stark().num_queries;
+        let tampered = |index: usize| {
+            let mut objects = honest.clone();
+            let ProofObject::FieldElements(values) = &mut objects[index] else {
+                panic!("object {index} holds no field elements");
+            };
+            values[0] += Field::new().one();
+            verify(objects)
+        };
+
+        // The terminals come before the combined root, and the opened rows of
+        // the first table right after it: current rows, next rows, the
+        // authentication path, then the same for its extension columns.
+        let terminals = 5;
+        let base_row = 8;
+        let next_row = base_row + num_queries;
+        let extension_row = base_row + 2 * num_queries + 1;
+        let combined_value = honest.len() - 2;
+        for index in [
+            terminals,
+            terminals + 1,
+            base_row,
+            next_row,
+            extension_row,
+            combined_value,
+        ] {
+            assert!(tampered(index).is_none());
+        }
+
+        let mut truncated = honest.clone();
+        truncated.pop();
+        assert!(verify(truncated).is_none());
+    }
+
+    #[test]
+    fn combined_codeword_is_checked_against_the_tables() {
+        let mut proof_stream = ProofStream::new();
+        let combined = stark()
+            .prove(&[&X, &Y], &base_tables(), &[], &mut proof_stream)
+            .codeword;
+        let honest = objects(&proof_stream);
+        assert_eq!(with_combined_codeword(&honest, &combined), honest);
+
+        // Correctly opened, but not what the tables give at any point.
+        let one = Field::new().one();
+        let forged: Vec<FieldElement> = combined.iter().map(|c| *c + one).collect();
+        assert!(verify(with_combined_codeword(&honest, &forged)).is_none());
+    }
+
+    #[test]
+    fn heights_are_validated() {
+        let honest = objects(&prove());
+        assert_eq!(verify(honest.clone()).unwrap().heights, vec![16, 4]);
+
+        for heights in [
+            vec![16],
+            vec![16, 3],
+            vec![16, 0],
+            vec![1 << 11, 4],
+            vec![1 << 63, 4],
+        ] {
+            let mut objects = honest.clone();
+            objects[0] = ProofObject::Heights(heights);
+            assert!(verify(objects).is_none());
+        }
+    }
+
+    #[test]
+    fn streaming_from_disk_sends_the_same_proof() {
+        let mut proof_stream = ProofStream::new();
+        let combined = stark().prove(&[&X, &Y], &base_tables(), &[], &mut proof_stream);
+        let honest = objects(&proof_stream);
+        let claim = verify(honest.clone()).unwrap();
+
+        // Windows smaller than a coset, not dividing the domain, and larger
+        // than the smaller table.
+        let storage = Storage::Disk(std::env::temp_dir());
+        for window in [1, 5, 32] {
+            let mut proof_stream = ProofStream::new();
+            let streamed = stark()
+                .prove_streaming(
+                    &[&X, &Y],
+                    &base_stores(&storage),
+                    &[],
+                    &storage,
+                    window,
+                    &mut testing::rng(0),
+                    &mut proof_stream,
+                )
+                .unwrap();
+
+            let streamed_claim = verify(objects(&proof_stream)).unwrap();
+            assert_eq!(streamed_claim.base_roots, claim.base_roots);
+            assert_eq!(streamed_claim.extension_roots, claim.extension_roots);
+            assert_eq!(objects(&proof_stream), honest);
+            assert_eq!(
+                streamed
+                    .codeword
+                    .read(0, 0, combined.domain.size())
+                    .unwrap(),
+                combined.codeword
+            );
+            assert_eq!(streamed.memory.stored_bytes, 0);
+        }
+    }
+
+    #[test]
+    fn stored_bytes_stay_bounded_on_disk() {
+        let field = Field::new();
+        let stored_bytes = |rows: u64, storage: &Storage| {
+            let base_tables: Vec<ColumnStore> = [3..3 + rows, 7..11]
+                .into_iter()
+                .map(|range| {
+                    let column = range.map(|v| FieldElement::from_u64(v, field)).collect();
+                    store_columns(&[column], storage, field).unwrap()
+                })
+                .collect();
+            stark()
+                .prove_streaming(
+                    &[&X, &Y],
+                    &base_tables,
+                    &[],
+                    storage,
+                    16,
+                    &mut testing::rng(0),
+                    &mut ProofStream::<Sha3>::new(),
+                )
+                .unwrap()
+                .memory
+                .stored_bytes
+        };
+
+        // In memory, every column counts, the base columns included, so eight
+        // times the rows take about eight times the bytes.
+        let short = stored_bytes(13, &Storage::Memory);
+        let long = stored_bytes(100, &Storage::Memory);
+        assert!(short > 0 && long > 4 * short);
+
+        // On disk, none of them is kept in memory, however long the trace.
+        let storage = Storage::Disk(std::env::temp_dir());
+        assert_eq!(stored_bytes(13, &storage), 0);
+        assert_eq!(stored_bytes(100, &storage), 0);
+    }
+
+    #[test]
+    fn zero_knowledge_proofs_verify_and_depend_on_the_randomness() {
+        let hiding = MultiTableStark {
+            zero_knowledge: true,
+            ..stark()
+        };
+        let prove = |seed: u64| {
+            let mut proof_stream = ProofStream::<Sha3>::new();
+            let streamed = hiding
+                .prove_streaming(
+                    &[&X, &Y],
+                    &base_stores(&Storage::Memory),
+                    &[],
+                    &Storage::Memory,
+                    usize::MAX,
+                    &mut testing::rng(seed),
+                    &mut proof_stream,
+                )
+                .unwrap();
+            let codeword = streamed
+                .codeword
+                .read(0, 0, streamed.domain.size())
+                .unwrap();
+            let degree = EvaluationForm::new(streamed.domain, codeword)
+                .to_polynomial()
+                .degree();
+            (proof_stream, degree, streamed.degree_bound)
+        };
+        let verify = |proof_stream: &ProofStream<Sha3>| {
+            let mut proof_stream = proof_stream.clone();
+            hiding.verify(&[&X, &Y], Field::new(), &mut proof_stream)
+        };
+
+        let (first, degree, degree_bound) = prove(1);
+        let claim = verify(&first).unwrap();
+        assert!(claim.mask_root.is_some());
+        assert_eq!(claim.degree_bound, degree_bound);
+        assert!(degree <= degree_bound as i128);
+        // 32 randomizers on top of 16 rows lift the degree of every column
+        // to 47, and the product constraints to 94 - 15.
+        assert_eq!(degree_bound, 79);
+
+        assert_eq!(objects(&prove(1).0), objects(&first));
+        let (second, _, _) = prove(2);
+        assert!(verify(&second).is_some());
+        assert_ne!(objects(&second)[1], objects(&first)[1]);
+
+        // A plain verifier doesn't accept a zero-knowledge proof.
+        assert!(stark()
+            .verify(&[&X, &Y], Field::new(), &mut first.clone())
+            .is_none());
+    }
+
+    #[test]
+    fn hiding_commitments_are_salted() {
+        // The same rows committed without salts and with two sets of salts.
+        let field = Field::new();
+        let trace = pad(&X, &base_stores(&Storage::Memory)[0], &Storage::Memory, 16).unwrap();
+        let domain = stark().evaluation_domain(&[16], field).unwrap();
+        let roots: Vec<Digest> = [None, Some([1; 32]), Some([2; 32])]
+            .into_iter()
+            .map(|salt_seed| {
+                let codewords = Codewords {
+                    salt_seed,
+                    ..low_degree_extend(&trace, &domain, &[], &[vec![]], &Storage::Memory).unwrap()
+                };
+                commit::<Sha3>(&codewords, 32).unwrap()
+            })
+            .collect();
+        assert_ne!(roots[0], roots[1]);
+        assert_ne!(roots[0], roots[2]);
+        assert_ne!(roots[1], roots[2]);
+
+        let hiding = MultiTableStark {
+            zero_knowledge: true,
+            ..stark()
+        };
+        let prove = |seed: u64| {
+            let mut proof_stream = ProofStream::<Sha3>::new();
+            hiding
+                .prove_streaming(
+                    &[&X, &Y],
+                    &base_stores(&Storage::Memory),
+                    &[],
+                    &Storage::Memory,
+                    usize::MAX,
+                    &mut testing::rng(seed),
+                    &mut proof_stream,
+                )
+                .unwrap();
+            objects(&proof_stream)
+        };
+        let verify = |objects: Vec<ProofObject>| {
+            let mut proof_stream = ProofStream::<Sha3>::new();
+            for object in objects {
+                proof_stream.push(object);
+            }
+            hiding.verify(&[&X, &Y], Field::new(), &mut proof_stream)
+        };
+
+        let first = verify(prove(3)).unwrap();
+        let second = verify(prove(4)).unwrap();
+        for (a, b) in first.base_roots.iter().zip(&second.base_roots) {
+            assert_ne!(a, b);
+        }
+        for (a, b) in first.extension_roots.iter().zip(&second.extension_roots) {
+            assert_ne!(a, b);
+        }
+        assert_ne!(first.mask_root, second.mask_root);
+        assert_ne!(first.combined_root, second.combined_root);
+
+        // Every opening carries its salts, and they have to be the committed
+        // ones.
+        let honest = prove(3);
+        let salts: Vec<usize> = (0..honest.len())
+            .filter(|i| matches!(honest[*i], ProofObject::Salts(_)))
+            .collect();
+        assert_eq!(salts.len(), 6);
+        for index in salts {
+            let mut tampered = honest.clone();
+            let ProofObject::Salts(values) = &mut tampered[index] else {
+                unreachable!()
+            };
+            values[0][0] ^= 1;
+            assert!(verify(tampered).is_none());
+
+            let mut missing = honest.clone();
+            missing.remove(index);
+            assert!(verify(missing).is_none());
+        }
+    }
+
+    #[test]
+    fn constraints_can_use_periodic_and_preprocessed_columns() {
+        let field = Field::new();
+        let one = field.one();
+        let table = Schedule {
+            steps: [1, 2, 3, 4],
+        };
+        let fixed: Vec<FieldElement> = (0..16)
+            .map(|i| FieldElement::from_u64(i * i + 2, field))
+            .collect();
+        let mut x = vec![one];
+        for i in 0..15 {
+            x.push(x[i] + FieldElement::from_u64(table.steps[i % 4], field));
+        }
+        let y = x.iter().zip(&fixed).map(|(x, p)| *x * *p).collect();
+        let base_tables = vec![vec![x, y]];
+
+        let domain = stark().evaluation_domain(&[16], field).unwrap();
+        let commit = |column: Vec<FieldElement>| {
+            PreprocessedColumns::<Sha3>::commit(
+                &["p"],
+                &[column],
+                &Domain::new(16, field).unwrap(),
+                &domain,
+            )
+        };
+        let with_root = |root: Digest| MultiTableStark {
+            preprocessed_roots: vec![Some(root)],
+            ..stark()
+        };
+        // The proof, and whether its combined codeword is within the degree
+        // bound.
+        let prove = |table: &Schedule, columns: &PreprocessedColumns<Sha3>| {
+            let mut proof_stream = ProofStream::new();
+            let combined = with_root(columns.root()).prove(
+                &[table],
+                &base_tables,
+                &[Some(columns)],
+                &mut proof_stream,
+            );
+            let degree = EvaluationForm::new(combined.domain, combined.codeword)
+                .to_polynomial()
+                .degree();
+            (
+                objects(&proof_stream),
+                degree <= combined.degree_bound as i128,
+            )
+        };
+        let verify = |table: &Schedule, root: Digest, objects: Vec<ProofObject>| {
+            let mut proof_stream = ProofStream::<Sha3>::new();
+            for object in objects {
+                proof_stream.push(object);
+            }
+            with_root(root).verify(&[table], field, &mut proof_stream)
+        };
+
+        let committed = commit(fixed.clone());
+        let (honest, low_degree) = prove(&table, &committed);
+        assert!(low_degree);
+        assert!(verify(&table, committed.root(), honest.clone()).is_some());
+
+        // The opened preprocessed rows have to belong to the root the verifier
+        // knows. They follow the heights, two roots, the terminals, the
+        // combined root and the openings of the base and extension columns.
+        let other = commit(fixed.iter().map(|p| *p + one).collect());
+        assert!(verify(&table, other.root(), honest.clone()).is_none());
+        let preprocessed_row = 5 + 2 * (2 * stark().num_queries + 1);
+        let mut tampered = honest.clone();
+        let ProofObject::FieldElements(row) = &mut tampered[preprocessed_row] else {
+            panic!("object {preprocessed_row} holds no field elements");
+        };
+        row[0] += one;
+        assert!(verify(&table, committed.root(), tampered).is_none());
+
+        // The trace only satisfies the constraints with these preprocessed
+        // and periodic columns. With others, the combined codeword isn't of
+        // low degree.
+        assert!(!prove(&table, &other).1);
+        let skewed = Schedule {
+            steps: [1, 2, 3, 5],
+        };
+        assert!(!prove(&skewed, &committed).1);
+
+        // The verifier evaluates the periodic columns itself.
+        assert!(verify(&skewed, committed.root(), honest).is_none());
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{algebra::FieldElement, parallel::*};
+
+/// Number theoretic transform: evaluates the polynomial with coefficients
+/// `values` on the powers of `root`, which must be a primitive `n`th root of
+/// unity where `n = values.len()` is a power of two.
+pub fn ntt(values: &[FieldElement], root: &FieldElement) -> Vec<FieldElement> {
+    let n = values.len();
+    assert!(n.is_power_of_two(), "ntt length has to be a power of two");
+
+    // Iterative radix-2 Cooley-Tukey on the bit-reversed input. The twiddles
+    // of a layer are computed up front so that every butterfly of the layer
+    // is independent of the others.
+    let mut acc: Vec<FieldElement> = into_iter!(0..n)
+        .map(|i| values[bit_reverse(i, n)])
+        .collect();
+    let mut half = 1;
+    while half < n {
+        let step = root.modexp(n / (2 * half));
+        let mut twiddles = Vec::with_capacity(half);
+        let mut twiddle = root.field.one();
+        for _ in 0..half {
+            twiddles.push(twiddle);
+            twiddle *= step;
+        }
+
+        // Blocks of a layer run in parallel, the butterflies within a block
+        // sequentially: splitting them further only pays off in the last few
+        // layers and costs every small one.
+        chunks_mut!(acc, 2 * half).for_each(|chunk| {
+            let (lo, hi) = chunk.split_at_mut(half);
+            for ((a, b), twiddle) in lo.iter_mut().zip(hi.iter_mut()).zip(&twiddles) {
+                let t = *twiddle * *b;
+                *b = *a - t;
+                *a += t;
+            }
+        });
+        half *= 2;
+    }
+
+    acc
+}
+
+/// Inverse of `ntt`: interpolates the coefficients of the polynomial taking
+/// `values` on the powers of `root`.
+pub fn intt(values: &[FieldElement], root: &FieldElement) -> Vec<FieldElement> {
+    let field = root.field;
+    let n_inverse = FieldElement::from_u64(values.len() as u64, field).inverse();
+
+    ntt(values, &root.inverse())
+        .into_iter()
+        .map(|v| v * n_inverse)
+        .collect()
+}
+
+fn bit_reverse(index: usize, n: usize) -> usize {
+    if n == 1 {
+        index
+    } else {
+        index.reverse_bits() >> (usize::BITS - n.trailing_zeros())
+    }
+}
@@ -0,0 +1,109 @@
+// Iterators that run on the rayon thread pool with the `parallel` feature and
+// sequentially without it. Only use them for pure maps whose results get
+// collected in order, so both builds produce bit-identical proofs. Import
+// this module with a glob so the rayon combinators are in scope as well.
+
+#[cfg(feature = "parallel")]
+pub(crate) use rayon::prelude::*;
+
+macro_rules! iter {
+    ($e:expr) => {{
+        #[cfg(feature = "parallel")]
+        let result = $e.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let result = $e.iter();
+        result
+    }};
+}
+
+macro_rules! into_iter {
+    ($e:expr) => {{
+        #[cfg(feature = "parallel")]
+        let result = $e.into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let result = $e.into_iter();
+        result
+    }};
+}
+
+macro_rules! chunks {
+    ($e:expr, $size:expr) => {{
+        #[cfg(feature = "parallel")]
+        let result = $e.par_chunks($size);
+        #[cfg(not(feature = "parallel"))]
+        let result = $e.chunks($size);
+        result
+    }};
+}
+
+macro_rules! chunks_mut {
+    ($e:expr, $size:expr) => {{
+        #[cfg(feature = "parallel")]
+        let result = $e.par_chunks_mut($size);
+        #[cfg(not(feature = "parallel"))]
+        let result = $e.chunks_mut($size);
+        result
+    }};
+}
+
+pub(crate) use {chunks, chunks_mut, into_iter, iter};
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        algebra::FieldElement,
+        domain::Domain,
+        hash::{Digest, Hasher, Sha3},
+        merkle::{leaf_bytes, MerkleRootBuilder, MerkleTree},
+        ntt::ntt,
+        testing,
+        unipolynomial::UPolynomial,
+    };
+
+    // Both builds check the same constants, which pins the output of the
+    // parallel build to that of the serial one.
+
+    const SIZE: usize = 256;
+
+    fn hex(digest: &Digest) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn codeword() -> Vec<FieldElement> {
+        let mut rng = testing::rng(45);
+        let coefficients = rng.elements(SIZE);
+        let domain = Domain::new(SIZE, rng.field()).unwrap();
+        let values = ntt(&coefficients, &domain.root());
+
+        let polynomial = UPolynomial::new(coefficients);
+        for (value, x) in values.iter().zip(domain.iter()) {
+            assert_eq!(*value, polynomial.evaluate(&x));
+        }
+
+        values
+    }
+
+    #[test]
+    fn ntt_is_the_same_in_both_builds() {
+        assert_eq!(
+            hex(&Sha3::hash(&leaf_bytes(&codeword()))),
+            "50450ee1f32d89cec214bb6fdf3753b160b5dab86e5456b8326a91d3118efe55"
+        );
+    }
+
+    #[test]
+    fn merkle_roots_are_the_same_in_both_builds() {
+        let leaves: Vec<Vec<u8>> = codeword().iter().map(|v| leaf_bytes(&[*v])).collect();
+        let root = MerkleTree::<Sha3>::commit(&leaves).root();
+
+        let mut builder = MerkleRootBuilder::<Sha3>::new();
+        for window in leaves.chunks(7) {
+            builder.push(window);
+        }
+        assert_eq!(builder.root(), root);
+        assert_eq!(
+            hex(&root),
+            "1ba5cd9a4533eaf3bc0ebd439c7d26c3815c91dd39234553bbeb727155bab233"
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{
+    algebra::{Field, FieldElement, FieldError},
+    domain::Domain,
+    evaluation::EvaluationForm,
+    unipolynomial::UPolynomial,
+};
+
+/// Column repeating `values` with a power-of-two period `k` dividing the trace
+/// length, such as round constants or a decoding table.
+///
+/// Neither side commits to it: over a trace of `n` rows the column is
+/// `q(X^{n/k})` for the polynomial `q` interpolating `values` over the
+/// subgroup of order `k`, so the verifier evaluates it wherever it needs to
+/// with a `k`-term polynomial.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeriodicColumn {
+    name: String,
+    values: Vec<FieldElement>,
+    polynomial: UPolynomial,
+}
+
+#[allow(dead_code)]
+impl PeriodicColumn {
+    pub fn new(name: &str, values: Vec<FieldElement>, field: Field) -> Result<Self, FieldError> {
+        let domain = Domain::new(values.len(), field)?;
+        let polynomial = EvaluationForm::new(domain, values.clone()).to_polynomial();
+
+        Ok(PeriodicColumn {
+            name: name.to_string(),
+            values,
+            polynomial,
+        })
+    }
+
+    /// Name the constraints refer to the column by.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn values(&self) -> &[FieldElement] {
+        &self.values
+    }
+
+    pub fn period(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn polynomial(&self) -> &UPolynomial {
+        &self.polynomial
+    }
+
+    /// Degree of the column as a polynomial over a trace of `trace_length`
+    /// rows, for the degree analysis.
+    pub fn degree_bound(&self, trace_length: usize) -> usize {
+        (self.period() - 1) * (trace_length / self.period())
+    }
+
+    /// Value of the column at `x` for a trace of `trace_length` rows.
+    pub fn evaluate(&self, x: &FieldElement, trace_length: usize) -> FieldElement {
+        self.polynomial
+            .evaluate(&x.modexp(self.stride(trace_length)))
+    }
+
+    /// Values of the column over `domain`. The map `x ↦ x^{n/k}` sends the
+    /// domain onto a smaller coset, so only that many evaluations are made.
+    pub fn evaluate_domain(&self, domain: &Domain, trace_length: usize) -> Vec<FieldElement> {
+        let stride = self.stride(trace_length);
+        assert!(
+            domain.size() >= trace_length,
+            "domain has to be at least as large as the trace"
+        );
+
+        let image = Domain::coset(domain.size() / stride, domain.offset().modexp(stride))
+            .expect("subgroups of the domain exist in its field");
+        let values = EvaluationForm::from_polynomial(&self.polynomial, image)
+            .values()
+            .to_vec();
+
+        (0..domain.size())
+            .map(|i| values[i % values.len()])
+            .collect()
+    }
+
+    fn stride(&self, trace_length: usize) -> usize {
+        assert!(
+            trace_length.is_power_of_two() && trace_length >= self.period(),
+            "period has to divide the trace length"
+        );
+        trace_length / self.period()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const TRACE_LENGTH: usize = 32;
+
+    #[test]
+    fn column_repeats_its_cycle() {
+        let field = Field::new();
+        let mut rng = testing::rng(43);
+        let trace_domain = Domain::new(TRACE_LENGTH, field).unwrap();
+        for period in [1, 2, 8, TRACE_LENGTH] {
+            let column = PeriodicColumn::new("k", rng.elements(period), field).unwrap();
+            for (i, x) in trace_domain.iter().enumerate() {
+                assert_eq!(
+                    column.evaluate(&x, TRACE_LENGTH),
+                    column.values()[i % period]
+                );
+            }
+
+            let evaluation_domain = rng.coset(4 * TRACE_LENGTH).unwrap();
+            let expected: Vec<FieldElement> = evaluation_domain
+                .iter()
+                .map(|x| column.evaluate(&x, TRACE_LENGTH))
+                .collect();
+            assert_eq!(
+                column.evaluate_domain(&evaluation_domain, TRACE_LENGTH),
+                expected
+            );
+
+            // q(X^{n/k}) over the trace domain stays within the degree bound.
+            let stretched = EvaluationForm::new(
+                trace_domain,
+                trace_domain
+                    .iter()
+                    .map(|x| column.evaluate(&x, TRACE_LENGTH))
+                    .collect(),
+            )
+            .to_polynomial();
+            assert!(stretched.degree() <= column.degree_bound(TRACE_LENGTH) as i128);
+        }
+    }
+}
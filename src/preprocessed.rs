@@ -0,0 +1,187 @@
+use crate::{
+    algebra::FieldElement,
+    domain::Domain,
+    evaluation::EvaluationForm,
+    hash::{Digest, Hasher, Sha3},
+    merkle::{leaf_bytes, MerkleTree},
+    parallel::*,
+    proof_stream::{ProofObject, ProofStream},
+    unipolynomial::UPolynomial,
+};
+
+/// Fixed trace columns that don't depend on the witness, such as a program or
+/// a lookup table. They get committed once over the evaluation domain, and
+/// the Merkle root identifies them from then on: it belongs to the
+/// verification key instead of the proof, and every proof only opens rows
+/// against it.
+#[derive(Debug, Clone)]
+pub struct PreprocessedColumns<H = Sha3> {
+    names: Vec<String>,
+    polynomials: Vec<UPolynomial>,
+    codewords: Vec<Vec<FieldElement>>,
+    tree: MerkleTree<H>,
+}
+
+#[allow(dead_code)]
+impl<H: Hasher> PreprocessedColumns<H> {
+    pub fn commit(
+        names: &[&str],
+        columns: &[Vec<FieldElement>],
+        trace_domain: &Domain,
+        evaluation_domain: &Domain,
+    ) -> Self {
+        assert!(
+            names.len() == columns.len(),
+            "every preprocessed column needs a name"
+        );
+
+        let polynomials: Vec<UPolynomial> = iter!(columns)
+            .map(|column| EvaluationForm::new(*trace_domain, column.clone()).to_polynomial())
+            .collect();
+        let codewords: Vec<Vec<FieldElement>> = iter!(polynomials)
+            .map(|p| {
+                EvaluationForm::from_polynomial(p, *evaluation_domain)
+                    .values()
+                    .to_vec()
+            })
+            .collect();
+
+        let leaves: Vec<Vec<u8>> = into_iter!(0..evaluation_domain.size())
+            .map(|i| leaf_bytes(&row(&codewords, i)))
+            .collect();
+
+        PreprocessedColumns {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            polynomials,
+            codewords,
+            tree: MerkleTree::commit(&leaves),
+        }
+    }
+
+    pub fn root(&self) -> Digest {
+        self.tree.root()
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn polynomials(&self) -> &[UPolynomial] {
+        &self.polynomials
+    }
+
+    pub fn codewords(&self) -> &[Vec<FieldElement>] {
+        &self.codewords
+    }
+
+    /// Values of every column at point `index` of the evaluation domain.
+    pub fn row(&self, index: usize) -> Vec<FieldElement> {
+        row(&self.codewords, index)
+    }
+
+    /// Sends the rows at `indices` of the evaluation domain and one batched
+    /// authentication path for them.
+    pub fn open(&self, proof_stream: &mut ProofStream<H>, indices: &[usize]) {
+        for i in indices {
+            proof_stream.push(ProofObject::FieldElements(row(&self.codewords, *i)));
+        }
+        proof_stream.push(ProofObject::MerkleProof(self.tree.open_many(indices)));
+    }
+
+    /// Reads the rows sent by `open` and checks them against `root`, or
+    /// returns `None` if they don't belong to the committed columns.
+    pub fn verify(
+        proof_stream: &mut ProofStream<H>,
+        root: &Digest,
+        num_columns: usize,
+        evaluation_domain: &Domain,
+        indices: &[usize],
+    ) -> Option<Vec<Vec<FieldElement>>> {
+        let mut rows = Vec::with_capacity(indices.len());
+        for _ in indices {
+            let row = proof_stream.pull()?.into_field_elements()?;
+            if row.len() != num_columns {
+                return None;
+            }
+            rows.push(row);
+        }
+        let proof = proof_stream.pull()?.into_merkle_proof()?;
+
+        let leaves: Vec<Vec<u8>> = rows.iter().map(|row| leaf_bytes(row)).collect();
+        MerkleTree::<H>::verify_many(
+            root,
+            evaluation_domain.size(),
+            indices,
+            &leaves,
+            None,
+            &proof,
+        )
+        .then_some(rows)
+    }
+}
+
+fn row(codewords: &[Vec<FieldElement>], index: usize) -> Vec<FieldElement> {
+    codewords.iter().map(|codeword| codeword[index]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{algebra::Field, testing};
+
+    const TRACE_LENGTH: usize = 8;
+
+    #[test]
+    fn openings_round_trip() {
+        let field = Field::new();
+        let mut rng = testing::rng(44);
+        let trace_domain = Domain::new(TRACE_LENGTH, field).unwrap();
+        let evaluation_domain = rng.coset(4 * TRACE_LENGTH).unwrap();
+        let columns = vec![rng.elements(TRACE_LENGTH), rng.elements(TRACE_LENGTH)];
+        let committed = PreprocessedColumns::<Sha3>::commit(
+            &["program", "table"],
+            &columns,
+            &trace_domain,
+            &evaluation_domain,
+        );
+        let root = committed.root();
+
+        // The codewords extend the columns.
+        for (polynomial, column) in committed.polynomials().iter().zip(&columns) {
+            assert_eq!(
+                &polynomial.evaluate_domain(&trace_domain.elements()),
+                column
+            );
+        }
+
+        let indices = [3, 17, 4, 30];
+        let verify = |proof_stream: &mut ProofStream, root: &Digest| {
+            PreprocessedColumns::<Sha3>::verify(proof_stream, root, 2, &evaluation_domain, &indices)
+        };
+        let mut proof_stream = ProofStream::new();
+        committed.open(&mut proof_stream, &indices);
+        let rows = verify(&mut proof_stream.clone(), &root).unwrap();
+        for (row, i) in rows.iter().zip(indices) {
+            let x = evaluation_domain.element(i);
+            let expected: Vec<FieldElement> = committed
+                .polynomials()
+                .iter()
+                .map(|p| p.evaluate(&x))
+                .collect();
+            assert_eq!(row, &expected);
+        }
+
+        assert!(verify(&mut proof_stream, &Sha3::hash(b"other")).is_none());
+
+        let mut tampered = ProofStream::new();
+        for (k, i) in indices.iter().enumerate() {
+            let mut row = row(committed.codewords(), *i);
+            if k == 2 {
+                row[1] += field.one();
+            }
+            tampered.push(ProofObject::FieldElements(row));
+        }
+        tampered.push(ProofObject::MerkleProof(committed.tree.open_many(&indices)));
+        assert!(verify(&mut tampered, &root).is_none());
+    }
+}
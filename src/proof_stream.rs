@@ -0,0 +1,322 @@
+use std::marker::PhantomData;
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+
+use crate::{
+    algebra::{Field, FieldElement},
+    hash::{Digest, HashId, Hasher, Sha3},
+    merkle::MerkleProof,
+};
+
+/// Transcript bytes per sampled field element, enough for the bias modulo a
+/// 128-bit prime to be negligible.
+pub const SAMPLE_BYTES: usize = 24;
+
+/// Transcript bytes per sampled query index.
+pub const INDEX_BYTES: usize = 8;
+
+/// Everything a prover can send to the verifier.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ProofObject {
+    FieldElements(Vec<FieldElement>),
+    Nonce(u64),
+    MerkleRoot(Digest),
+    MerkleProof(MerkleProof),
+    /// Padded heights of the tables of a multi-table proof.
+    Heights(Vec<u64>),
+    /// Salts of the leaves opened from a salted Merkle tree, in the order the
+    /// leaves were opened.
+    Salts(Vec<Digest>),
+}
+
+impl ProofObject {
+    pub fn into_field_elements(self) -> Option<Vec<FieldElement>> {
+        match self {
+            ProofObject::FieldElements(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    pub fn into_merkle_root(self) -> Option<Digest> {
+        match self {
+            ProofObject::MerkleRoot(root) => Some(root),
+            _ => None,
+        }
+    }
+
+    pub fn into_merkle_proof(self) -> Option<MerkleProof> {
+        match self {
+            ProofObject::MerkleProof(proof) => Some(proof),
+            _ => None,
+        }
+    }
+
+    pub fn into_heights(self) -> Option<Vec<u64>> {
+        match self {
+            ProofObject::Heights(heights) => Some(heights),
+            _ => None,
+        }
+    }
+
+    pub fn into_salts(self) -> Option<Vec<Digest>> {
+        match self {
+            ProofObject::Salts(salts) => Some(salts),
+            _ => None,
+        }
+    }
+}
+
+/// Parameters a proof was generated with. They are part of the proof and are
+/// absorbed into every Fiat-Shamir challenge, so the verifier can check them
+/// against its own policy before trusting anything else.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofParameters {
+    /// Leading zero bits the proof-of-work nonce has to produce before the
+    /// FRI queries get sampled.
+    pub grinding_bits: u32,
+}
+
+/// Transcript of the messages a prover sends, doubling as the source of the
+/// verifier's challenges through the Fiat-Shamir transform: every challenge is
+/// a hash of the messages exchanged so far.
+///
+/// Challenges and grinding use `H`. Its id leads the serialized proof and is
+/// absorbed along with the parameters, so a proof only ever verifies under
+/// the hash it was made with. Deserializing a proof whose id isn't `H::ID`
+/// fails, which also ties the proof to the `MerkleTree<H>` its roots were
+/// built with.
+#[derive(Debug, Clone, Serialize)]
+#[serde(bound = "")]
+pub struct ProofStream<H = Sha3> {
+    hash: HashId,
+    parameters: ProofParameters,
+    objects: Vec<ProofObject>,
+    #[serde(skip)]
+    read_index: usize,
+    #[serde(skip)]
+    hasher: PhantomData<H>,
+}
+
+impl<'de, H: Hasher> Deserialize<'de> for ProofStream<H> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Same shape as the derived `Serialize`.
+        #[derive(Deserialize)]
+        #[serde(rename = "ProofStream")]
+        struct Repr {
+            hash: HashId,
+            parameters: ProofParameters,
+            objects: Vec<ProofObject>,
+        }
+
+        let Repr {
+            hash,
+            parameters,
+            objects,
+        } = Repr::deserialize(deserializer)?;
+        if hash != H::ID {
+            return Err(D::Error::custom(format!(
+                "proof was made with {:?}, expected {:?}",
+                hash,
+                H::ID
+            )));
+        }
+
+        Ok(ProofStream {
+            hash,
+            parameters,
+            objects,
+            read_index: 0,
+            hasher: PhantomData,
+        })
+    }
+}
+
+impl<H: Hasher> Default for ProofStream<H> {
+    fn default() -> Self {
+        ProofStream::with_parameters(ProofParameters::default())
+    }
+}
+
+#[allow(dead_code)]
+impl<H: Hasher> ProofStream<H> {
+    pub fn new() -> Self {
+        ProofStream::default()
+    }
+
+    pub fn with_parameters(parameters: ProofParameters) -> Self {
+        ProofStream {
+            hash: H::ID,
+            parameters,
+            objects: Vec::new(),
+            read_index: 0,
+            hasher: PhantomData,
+        }
+    }
+
+    /// The hash recorded in the proof header.
+    pub fn hash(&self) -> HashId {
+        self.hash
+    }
+
+    pub fn parameters(&self) -> &ProofParameters {
+        &self.parameters
+    }
+
+    pub fn push(&mut self, object: ProofObject) {
+        self.objects.push(object);
+    }
+
+    /// The next object, or `None` once the proof is exhausted, which a
+    /// verifier treats like any other malformed proof.
+    pub fn pull(&mut self) -> Option<ProofObject> {
+        let object = self.objects.get(self.read_index)?.clone();
+        self.read_index += 1;
+
+        Some(object)
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
+    }
+
+    /// Fails if the proof was made with a different hash than `H`.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, bincode::Error> {
+        bincode::deserialize(bytes)
+    }
+
+    /// Challenge bytes derived from everything pushed so far.
+    pub fn prover_fiat_shamir(&self, num_bytes: usize) -> Vec<u8> {
+        self.fiat_shamir(&self.objects, num_bytes)
+    }
+
+    /// Challenge bytes derived from everything pulled so far, matching what the
+    /// prover saw at the same point of the protocol.
+    pub fn verifier_fiat_shamir(&self, num_bytes: usize) -> Vec<u8> {
+        self.fiat_shamir(&self.objects[..self.read_index], num_bytes)
+    }
+
+    /// Searches for a nonce such that hashing it with the transcript gives
+    /// `grinding_bits` leading zero bits, and pushes it. Meant to run right
+    /// before the FRI queries are sampled: every bit of work makes each
+    /// attempt at steering the queries twice as expensive, which is worth
+    /// about one query of soundness.
+    pub fn grind(&mut self) -> u64 {
+        assert!(
+            self.parameters.grinding_bits <= 64,
+            "can't grind for more than 64 bits"
+        );
+        let seed = self.prover_fiat_shamir(32);
+        let nonce = (0..=u64::MAX)
+            .find(|nonce| proof_of_work::<H>(&seed, *nonce) >= self.parameters.grinding_bits)
+            .unwrap();
+        self.push(ProofObject::Nonce(nonce));
+
+        nonce
+    }
+
+    /// Pulls the nonce pushed by `grind` and checks it with a single hash.
+    /// `grinding_bits` is the verifier's own requirement: a proof whose header
+    /// declares less work is rejected outright.
+    pub fn verify_grinding(&mut self, grinding_bits: u32) -> bool {
+        if self.parameters.grinding_bits < grinding_bits {
+            return false;
+        }
+
+        let seed = self.verifier_fiat_shamir(32);
+        match self.pull() {
+            Some(ProofObject::Nonce(nonce)) => {
+                proof_of_work::<H>(&seed, nonce) >= self.parameters.grinding_bits
+            }
+            _ => false,
+        }
+    }
+
+    fn fiat_shamir(&self, objects: &[ProofObject], num_bytes: usize) -> Vec<u8> {
+        let mut data = bincode::serialize(&(self.hash, self.parameters)).unwrap();
+        data.extend(bincode::serialize(objects).unwrap());
+        H::expand(&data, num_bytes)
+    }
+}
+
+/// Turns transcript bytes into `count` field elements.
+pub fn sample_weights(bytes: &[u8], count: usize, field: Field) -> Vec<FieldElement> {
+    assert!(
+        bytes.len() >= count * SAMPLE_BYTES,
+        "not enough transcript bytes for the weights"
+    );
+    bytes
+        .chunks(SAMPLE_BYTES)
+        .take(count)
+        .map(|chunk| field.sample(chunk))
+        .collect()
+}
+
+/// Turns transcript bytes into `count` indices into a domain of `size`
+/// points, which has to be a power of two for the reduction to be unbiased.
+pub fn sample_indices(bytes: &[u8], count: usize, size: usize) -> Vec<usize> {
+    assert!(
+        size.is_power_of_two(),
+        "domain size has to be a power of two"
+    );
+    assert!(
+        bytes.len() >= count * INDEX_BYTES,
+        "not enough transcript bytes for the indices"
+    );
+    bytes
+        .chunks(INDEX_BYTES)
+        .take(count)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()) as usize & (size - 1))
+        .collect()
+}
+
+/// Number of leading zero bits of `hash(seed ‖ nonce)`.
+fn proof_of_work<H: Hasher>(seed: &[u8], nonce: u64) -> u32 {
+    let bytes = H::expand(&[seed, &nonce.to_le_bytes()].concat(), 8);
+    u64::from_be_bytes(bytes.try_into().unwrap()).leading_zeros()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3;
+
+    #[test]
+    fn proofs_only_deserialize_under_their_own_hash() {
+        let mut proof_stream = ProofStream::<Sha3>::new();
+        proof_stream.push(ProofObject::MerkleRoot(Sha3::hash(b"root")));
+        let bytes = proof_stream.serialize();
+
+        assert!(ProofStream::<Sha3>::deserialize(&bytes).is_ok());
+        assert!(ProofStream::<Blake3>::deserialize(&bytes).is_err());
+        assert!(bincode::deserialize::<ProofStream<Blake3>>(&bytes).is_err());
+    }
+
+    #[test]
+    fn pull_stops_at_the_end_of_the_proof() {
+        let mut proof_stream = ProofStream::<Sha3>::new();
+        proof_stream.push(ProofObject::Nonce(7));
+
+        assert_eq!(proof_stream.pull(), Some(ProofObject::Nonce(7)));
+        assert_eq!(proof_stream.pull(), None);
+    }
+
+    #[test]
+    fn grinding_is_checked_against_the_verifiers_parameters() {
+        let grind = |grinding_bits: u32| {
+            let mut proof_stream =
+                ProofStream::<Sha3>::with_parameters(ProofParameters { grinding_bits });
+            proof_stream.push(ProofObject::MerkleRoot(Sha3::hash(b"root")));
+            proof_stream.grind();
+            ProofStream::<Sha3>::deserialize(&proof_stream.serialize()).unwrap()
+        };
+        let verify = |mut proof_stream: ProofStream<Sha3>, grinding_bits: u32| {
+            proof_stream.pull();
+            proof_stream.verify_grinding(grinding_bits)
+        };
+
+        assert!(verify(grind(8), 8));
+        assert!(verify(grind(8), 4));
+        assert!(!verify(grind(0), 8));
+        assert!(!verify(grind(4), 8));
+    }
+}
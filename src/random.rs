@@ -0,0 +1,162 @@
+use bigint::U256;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{OsRng, RngCore, SeedableRng};
+
+use crate::{
+    algebra::{Field, FieldElement, FieldError},
+    domain::Domain,
+    hash::Digest,
+    multipolynomial::MPolynomial,
+    unipolynomial::UPolynomial,
+};
+
+/// Reproducible source of field elements, polynomials and domains: ChaCha20
+/// keyed by a seed, so the same seed gives the same stream on every platform.
+/// Tests, benchmarks and the prover's zero-knowledge randomness all draw from
+/// it, and a proof meant to hide its witness seeds it from the OS.
+#[derive(Debug, Clone)]
+pub struct FieldRng {
+    rng: ChaCha20Rng,
+    field: Field,
+}
+
+#[allow(dead_code)]
+impl FieldRng {
+    pub fn new(seed: [u8; 32], field: Field) -> Self {
+        FieldRng {
+            rng: ChaCha20Rng::from_seed(seed),
+            field,
+        }
+    }
+
+    pub fn seed_from_u64(seed: u64, field: Field) -> Self {
+        FieldRng {
+            rng: ChaCha20Rng::seed_from_u64(seed),
+            field,
+        }
+    }
+
+    /// Seeded from the operating system, for randomness that must not be
+    /// replayed.
+    pub fn from_entropy(field: Field) -> Self {
+        let mut seed = [0; 32];
+        OsRng.fill_bytes(&mut seed);
+        Self::new(seed, field)
+    }
+
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    /// Uniform in `0..bound`.
+    pub fn below(&mut self, bound: usize) -> usize {
+        assert!(bound > 0, "can't sample from an empty range");
+        let bound = bound as u64;
+        // Largest multiple of `bound` that fits, so that every residue is
+        // equally likely.
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return (value % bound) as usize;
+            }
+        }
+    }
+
+    pub fn fill_bytes(&mut self, bytes: &mut [u8]) {
+        self.rng.fill_bytes(bytes);
+    }
+
+    /// Random 32 bytes, e.g. a Merkle leaf salt.
+    pub fn digest(&mut self) -> Digest {
+        let mut digest = [0; 32];
+        self.fill_bytes(&mut digest);
+        digest
+    }
+
+    /// Uniform field element, by rejection sampling values of the bit length
+    /// of the modulus until one is below it.
+    pub fn element(&mut self) -> FieldElement {
+        let bits = self.field.bits();
+        assert!(bits <= 128, "can only sample fields of up to 128 bits");
+        let mask = u128::MAX >> (128 - bits);
+        loop {
+            let value = (self.next_u64() as u128) << 64 | self.next_u64() as u128;
+            let value = value & mask;
+            let value = U256([value as u64, (value >> 64) as u64, 0, 0]);
+            if let Ok(element) = FieldElement::from_canonical(value, self.field) {
+                return element;
+            }
+        }
+    }
+
+    pub fn nonzero_element(&mut self) -> FieldElement {
+        loop {
+            let element = self.element();
+            if !element.is_zero() {
+                return element;
+            }
+        }
+    }
+
+    pub fn elements(&mut self, len: usize) -> Vec<FieldElement> {
+        (0..len).map(|_| self.element()).collect()
+    }
+
+    /// `len` pairwise distinct elements, e.g. an arbitrary interpolation
+    /// domain.
+    pub fn distinct_elements(&mut self, len: usize) -> Vec<FieldElement> {
+        let mut elements: Vec<FieldElement> = Vec::with_capacity(len);
+        while elements.len() < len {
+            let element = self.element();
+            if !elements.contains(&element) {
+                elements.push(element);
+            }
+        }
+
+        elements
+    }
+
+    /// Polynomial of exactly `degree`.
+    pub fn polynomial(&mut self, degree: usize) -> UPolynomial {
+        let mut coefficients = self.elements(degree);
+        coefficients.push(self.nonzero_element());
+
+        UPolynomial::new(coefficients)
+    }
+
+    /// Sparse polynomial in `num_variables` with up to `num_terms` terms of
+    /// degree at most `max_exponent` in every variable.
+    pub fn mpolynomial(
+        &mut self,
+        num_variables: usize,
+        num_terms: usize,
+        max_exponent: u128,
+    ) -> MPolynomial {
+        let terms: Vec<(Vec<u128>, FieldElement)> = (0..num_terms)
+            .map(|_| {
+                let exponents = (0..num_variables)
+                    .map(|_| self.below(max_exponent as usize + 1) as u128)
+                    .collect();
+                (exponents, self.element())
+            })
+            .collect();
+
+        MPolynomial::zero(num_variables) + MPolynomial::new(terms)
+    }
+
+    /// Coset of the subgroup of order `size` by a random offset outside of
+    /// the subgroup.
+    pub fn coset(&mut self, size: usize) -> Result<Domain, FieldError> {
+        loop {
+            let offset = self.nonzero_element();
+            if offset.modexp(size) != self.field.one() {
+                return Domain::coset(size, offset);
+            }
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::proof_stream::ProofParameters;
+
+/// Proximity parameter `m` of the Johnson bound analysis. Larger values
+/// tighten the query bound but loosen the field bound.
+const JOHNSON_PROXIMITY_PARAMETER: f64 = 3.0;
+
+/// FRI stops folding once a layer is this small and sends it in the clear.
+const LAST_LAYER_SIZE: usize = 64;
+
+/// Flags `SecurityParameters::with_args` understands.
+pub const USAGE: &str = "usage: logstark [--field-bits N] [--expansion-factor N] \
+[--num-queries N] [--grinding-bits N] [--air-degree N] [--trace-length N] [--num-columns N] \
+[--hash-bits N] [--target-bits N]";
+
+/// Knobs that decide how sound and how large a proof is, together with the
+/// shape of the AIR being proven.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SecurityParameters {
+    /// Bits of the prime field, i.e. `log2(p)` rounded up.
+    pub field_bits: u32,
+    /// Blowup from the trace domain to the evaluation domain.
+    pub expansion_factor: usize,
+    pub num_queries: usize,
+    pub grinding_bits: u32,
+    /// Largest total degree of the transition constraints.
+    pub air_degree: usize,
+    pub trace_length: usize,
+    pub num_columns: usize,
+    /// Output size of the commitment hash.
+    pub hash_bits: u32,
+    /// Conjectured bits of security below which `check` rejects the
+    /// parameters.
+    pub target_bits: u32,
+}
+
+/// The part of the protocol limiting the security of a parameter set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bottleneck {
+    /// The chance of a challenge hitting a bad field element, which only a
+    /// larger field (or a smaller evaluation domain) improves.
+    FieldSize,
+    /// The FRI queries, which more queries, a larger expansion factor or more
+    /// grinding improve.
+    Queries,
+    /// Collisions of the commitment hash, which only a wider hash improves.
+    HashCollisions,
+}
+
+impl fmt::Display for Bottleneck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bottleneck::FieldSize => write!(
+                f,
+                "the field is too small for the evaluation domain, use a larger field"
+            ),
+            Bottleneck::Queries => write!(
+                f,
+                "the query phase is too weak, raise the number of queries, the expansion factor or the grinding bits"
+            ),
+            Bottleneck::HashCollisions => write!(
+                f,
+                "the commitment hash is too narrow, use a hash with a wider digest"
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SecurityError {
+    message: String,
+}
+
+impl fmt::Display for SecurityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl StdError for SecurityError {}
+
+impl SecurityParameters {
+    /// Overrides the parameters given as `--name value` pairs, named like the
+    /// fields in kebab case, e.g. `--num-queries 64 --target-bits 128`.
+    pub fn with_args(
+        mut self,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self, SecurityError> {
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| SecurityError {
+                message: format!("{} needs a value", flag),
+            })?;
+            match flag.as_str() {
+                "--field-bits" => self.field_bits = parse(&flag, &value)?,
+                "--expansion-factor" => self.expansion_factor = parse(&flag, &value)?,
+                "--num-queries" => self.num_queries = parse(&flag, &value)?,
+                "--grinding-bits" => self.grinding_bits = parse(&flag, &value)?,
+                "--air-degree" => self.air_degree = parse(&flag, &value)?,
+                "--trace-length" => self.trace_length = parse(&flag, &value)?,
+                "--num-columns" => self.num_columns = parse(&flag, &value)?,
+                "--hash-bits" => self.hash_bits = parse(&flag, &value)?,
+                "--target-bits" => self.target_bits = parse(&flag, &value)?,
+                _ => {
+                    return Err(SecurityError {
+                        message: format!("unknown parameter {}", flag),
+                    })
+                }
+            }
+        }
+        if !self.expansion_factor.is_power_of_two() || !self.trace_length.is_power_of_two() {
+            return Err(SecurityError {
+                message: "expansion factor and trace length have to be powers of two".to_string(),
+            });
+        }
+
+        Ok(self)
+    }
+
+    /// Security in bits under the ethSTARK conjecture: every query is worth
+    /// `log2(1/ρ)` bits for a code of rate `ρ`.
+    pub fn conjectured_security_bits(&self) -> f64 {
+        self.conjectured().0
+    }
+
+    /// Provable security in bits from the Johnson bound list-decoding analysis
+    /// of FRI: every query is worth `log2(1 / (√ρ·(1 + 1/2m)))` bits, while the
+    /// commit phase costs `log2((m + ½)^7·|D|² / (3·ρ^{3/2}))` bits of field.
+    pub fn proven_security_bits(&self) -> f64 {
+        let m = JOHNSON_PROXIMITY_PARAMETER;
+        let rate = self.rate();
+        let per_query = -(rate.sqrt() * (1.0 + 1.0 / (2.0 * m))).log2();
+        let query = self.num_queries as f64 * per_query + self.grinding_bits as f64;
+
+        let domain = self.evaluation_domain_size();
+        let commit_error = (m + 0.5).powi(7) * domain * domain / (3.0 * rate.powf(1.5));
+        let field = self.field_bits as f64 - commit_error.log2();
+
+        self.limit(query, field).0
+    }
+
+    /// Which part of the protocol caps the conjectured security.
+    pub fn bottleneck(&self) -> Bottleneck {
+        self.conjectured().1
+    }
+
+    /// Estimated proof size in bytes: the trace and FRI roots, the
+    /// out-of-domain openings, and for every query the opened trace rows and
+    /// two sibling elements per FRI layer, each with its authentication path.
+    pub fn proof_size_bytes(&self) -> usize {
+        let element = self.field_bits.div_ceil(8) as usize;
+        let digest = self.hash_bits.div_ceil(8) as usize;
+        let domain = self.trace_length * self.expansion_factor;
+        let depth = domain.trailing_zeros() as usize;
+
+        let trace_root = digest;
+        let out_of_domain = 2 * self.num_columns * element;
+        let trace_openings =
+            self.num_queries * (2 * self.num_columns * element + 2 * depth * digest);
+
+        let mut fri = 0;
+        let mut layer = domain;
+        while layer > LAST_LAYER_SIZE {
+            let layer_depth = layer.trailing_zeros() as usize;
+            fri += digest + self.num_queries * (2 * element + 2 * layer_depth * digest);
+            layer /= 2;
+        }
+        fri += layer * element;
+
+        let nonce = if self.grinding_bits > 0 { 8 } else { 0 };
+
+        trace_root + out_of_domain + trace_openings + fri + nonce
+    }
+
+    /// Fails with an explanation of the bottleneck if the conjectured security
+    /// is below `target_bits`.
+    pub fn check(&self) -> Result<(), SecurityError> {
+        let target_bits = self.target_bits;
+        let bits = self.conjectured_security_bits();
+        if bits >= target_bits as f64 {
+            Ok(())
+        } else {
+            Err(SecurityError {
+                message: format!(
+                    "parameters give {:.1} bits of conjectured security ({:.1} proven), below the target of {} bits: {}",
+                    bits,
+                    self.proven_security_bits(),
+                    target_bits,
+                    self.bottleneck()
+                ),
+            })
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn proof_parameters(&self) -> ProofParameters {
+        ProofParameters {
+            grinding_bits: self.grinding_bits,
+        }
+    }
+
+    fn conjectured(&self) -> (f64, Bottleneck) {
+        let query = self.num_queries as f64 * -self.rate().log2() + self.grinding_bits as f64;
+        let field = self.field_bits as f64 - self.evaluation_domain_size().log2();
+
+        self.limit(query, field)
+    }
+
+    /// Rate of the code FRI tests: the composition polynomial has degree
+    /// about `(d - 1)·n` for constraints of degree `d` on a trace of length
+    /// `n`, against an evaluation domain of `expansion_factor·n` points.
+    fn rate(&self) -> f64 {
+        self.air_degree.saturating_sub(1).max(1) as f64 / self.expansion_factor as f64
+    }
+
+    fn evaluation_domain_size(&self) -> f64 {
+        (self.trace_length * self.expansion_factor) as f64
+    }
+
+    fn limit(&self, query: f64, field: f64) -> (f64, Bottleneck) {
+        let hash = self.hash_bits as f64 / 2.0;
+        let query = query.max(0.0);
+        let field = field.max(0.0);
+
+        if query <= field && query <= hash {
+            (query, Bottleneck::Queries)
+        } else if field <= hash {
+            (field, Bottleneck::FieldSize)
+        } else {
+            (hash, Bottleneck::HashCollisions)
+        }
+    }
+}
+
+fn parse<T: FromStr>(flag: &str, value: &str) -> Result<T, SecurityError> {
+    value.parse().map_err(|_| SecurityError {
+        message: format!("{} isn't a valid value for {}", value, flag),
+    })
+}
+
+impl fmt::Display for SecurityParameters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "conjectured security: {:.1} bits",
+            self.conjectured_security_bits()
+        )?;
+        writeln!(
+            f,
+            "proven security: {:.1} bits",
+            self.proven_security_bits()
+        )?;
+        writeln!(f, "estimated proof size: {} bytes", self.proof_size_bytes())?;
+        write!(f, "bottleneck: {}", self.bottleneck())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The defaults of `main`: 128-bit field, blowup 8, 48 queries, 16 bits
+    /// of grinding and constraints of degree 3 over 2^12 rows.
+    fn parameters() -> SecurityParameters {
+        SecurityParameters {
+            field_bits: 128,
+            expansion_factor: 8,
+            num_queries: 48,
+            grinding_bits: 16,
+            air_degree: 3,
+            trace_length: 1 << 12,
+            num_columns: 4,
+            hash_bits: 256,
+            target_bits: 100,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "{} isn't {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn conjectured_security_at_known_points() {
+        // Rate 2/8: 2 bits per query, 48·2 + 16 = 112, under the field's
+        // 128 - 15 = 113.
+        let parameters = parameters();
+        assert_close(parameters.conjectured_security_bits(), 112.0);
+        assert_eq!(parameters.bottleneck(), Bottleneck::Queries);
+
+        let small_field = SecurityParameters {
+            field_bits: 64,
+            ..parameters
+        };
+        assert_close(small_field.conjectured_security_bits(), 49.0);
+        assert_eq!(small_field.bottleneck(), Bottleneck::FieldSize);
+
+        let narrow_hash = SecurityParameters {
+            hash_bits: 160,
+            ..parameters
+        };
+        assert_close(narrow_hash.conjectured_security_bits(), 80.0);
+        assert_eq!(narrow_hash.bottleneck(), Bottleneck::HashCollisions);
+    }
+
+    #[test]
+    fn proven_security_at_known_points() {
+        // Per query -log2(√(1/4)·(1 + 1/6)) = log2(12/7) bits.
+        let per_query = (12.0f64 / 7.0).log2();
+        let parameters = parameters();
+        assert_close(parameters.proven_security_bits(), 48.0 * per_query + 16.0);
+
+        // The commit phase costs log2(3.5^7·2^30 / (3·(1/4)^{3/2})) bits of
+        // field, which a 64-bit field can't pay for.
+        let commit = 7.0 * 3.5f64.log2() + 30.0 - (3.0f64 / 8.0).log2();
+        let small_field = SecurityParameters {
+            field_bits: 64,
+            ..parameters
+        };
+        assert_close(small_field.proven_security_bits(), 64.0 - commit);
+        assert!(small_field.proven_security_bits() < small_field.conjectured_security_bits());
+    }
+
+    #[test]
+    fn check_enforces_the_target() {
+        assert!(parameters().check().is_ok());
+        let demanding = SecurityParameters {
+            target_bits: 120,
+            ..parameters()
+        };
+        assert!(demanding.check().is_err());
+    }
+
+    #[test]
+    fn arguments_override_the_defaults() {
+        let args = |args: &[&str]| parameters().with_args(args.iter().map(|arg| arg.to_string()));
+
+        assert_eq!(args(&[]).unwrap(), parameters());
+        let demanding = args(&["--target-bits", "120", "--num-queries", "40"]).unwrap();
+        assert_eq!(demanding.target_bits, 120);
+        assert_eq!(demanding.num_queries, 40);
+        assert_eq!(demanding.expansion_factor, 8);
+        assert!(demanding
+            .check()
+            .unwrap_err()
+            .to_string()
+            .ends_with(&Bottleneck::Queries.to_string()));
+
+        for wrong in [
+            &["--num-queries"][..],
+            &["--num-queries", "many"],
+            &["--grinding-bits", "-1"],
+            &["--expansion-factor", "6"],
+            &["--trace-length", "0"],
+            &["--queries", "40"],
+        ] {
+            assert!(args(wrong).is_err(), "{:?} accepted", wrong);
+        }
+    }
+}
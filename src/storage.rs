@@ -0,0 +1,232 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+use bigint::U256;
+
+use crate::algebra::{Field, FieldElement};
+
+/// Bytes of a field element in a column store.
+const ELEMENT_BYTES: usize = 16;
+
+/// Where a prover keeps its codewords.
+///
+/// Either way they are stored compactly, as the 16 byte canonical value of
+/// every element instead of the 32 byte `U256` of a `FieldElement`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Storage {
+    #[default]
+    Memory,
+    /// Anonymous files in the given directory, deleted when the store is
+    /// dropped. Only the windows read from them are ever held in memory.
+    #[allow(dead_code)]
+    Disk(PathBuf),
+}
+
+#[derive(Debug)]
+enum Column {
+    Memory(Vec<u128>),
+    Disk(File),
+}
+
+/// Columns of the same length, written and read back a window at a time.
+#[derive(Debug)]
+pub struct ColumnStore {
+    storage: Storage,
+    field: Field,
+    length: usize,
+    columns: Vec<Column>,
+}
+
+impl ColumnStore {
+    pub fn new(storage: &Storage, field: Field, length: usize) -> Self {
+        assert!(
+            field.bits() <= 8 * ELEMENT_BYTES,
+            "compact storage needs a modulus of at most 128 bits"
+        );
+
+        ColumnStore {
+            storage: storage.clone(),
+            field,
+            length,
+            columns: Vec::new(),
+        }
+    }
+
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn num_columns(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Bytes of column data held in memory, which is zero on disk.
+    pub fn resident_bytes(&self) -> usize {
+        self.columns
+            .iter()
+            .map(|column| match column {
+                Column::Memory(values) => values.len() * ELEMENT_BYTES,
+                Column::Disk(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Adds a column of zeros and returns its index.
+    pub fn add_column(&mut self) -> io::Result<usize> {
+        let column = match &self.storage {
+            Storage::Memory => Column::Memory(vec![0; self.length]),
+            Storage::Disk(directory) => {
+                let file = tempfile::tempfile_in(directory)?;
+                file.set_len((self.length * ELEMENT_BYTES) as u64)?;
+                Column::Disk(file)
+            }
+        };
+        self.columns.push(column);
+
+        Ok(self.columns.len() - 1)
+    }
+
+    /// Adds a whole column and returns its index.
+    pub fn push(&mut self, values: &[FieldElement]) -> io::Result<usize> {
+        assert!(values.len() == self.length, "column has the wrong length");
+        let index = self.add_column()?;
+        self.write(index, 0, values)?;
+
+        Ok(index)
+    }
+
+    /// Overwrites `values.len()` entries of `column` starting at `start`.
+    pub fn write(
+        &mut self,
+        column: usize,
+        start: usize,
+        values: &[FieldElement],
+    ) -> io::Result<()> {
+        assert!(
+            start + values.len() <= self.length,
+            "write past the end of the column"
+        );
+        match &mut self.columns[column] {
+            Column::Memory(stored) => {
+                for (stored, value) in stored[start..].iter_mut().zip(values) {
+                    *stored = to_compact(value);
+                }
+            }
+            Column::Disk(file) => {
+                let bytes: Vec<u8> = values
+                    .iter()
+                    .flat_map(|value| to_compact(value).to_le_bytes())
+                    .collect();
+                file.seek(SeekFrom::Start((start * ELEMENT_BYTES) as u64))?;
+                file.write_all(&bytes)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads `count` entries of `column` starting at `start`, wrapping around
+    /// the end of the column.
+    pub fn read(&self, column: usize, start: usize, count: usize) -> io::Result<Vec<FieldElement>> {
+        assert!(count <= self.length, "read longer than the column");
+        let start = start % self.length;
+        let head = count.min(self.length - start);
+
+        let mut values = self.read_contiguous(column, start, head)?;
+        values.extend(self.read_contiguous(column, 0, count - head)?);
+
+        Ok(values)
+    }
+
+    /// Reads rows `start..start + count` of every column, wrapping around the
+    /// end of the columns like `read`.
+    pub fn rows(&self, start: usize, count: usize) -> io::Result<Vec<Vec<FieldElement>>> {
+        let columns = (0..self.columns.len())
+            .map(|column| self.read(column, start, count))
+            .collect::<io::Result<Vec<Vec<FieldElement>>>>()?;
+
+        Ok((0..count)
+            .map(|i| columns.iter().map(|column| column[i]).collect())
+            .collect())
+    }
+
+    fn read_contiguous(
+        &self,
+        column: usize,
+        start: usize,
+        count: usize,
+    ) -> io::Result<Vec<FieldElement>> {
+        match &self.columns[column] {
+            Column::Memory(stored) => Ok(stored[start..start + count]
+                .iter()
+                .map(|value| from_compact(*value, self.field))
+                .collect()),
+            Column::Disk(file) => {
+                let mut file = file;
+                let mut bytes = vec![0; count * ELEMENT_BYTES];
+                file.seek(SeekFrom::Start((start * ELEMENT_BYTES) as u64))?;
+                file.read_exact(&mut bytes)?;
+
+                Ok(bytes
+                    .chunks(ELEMENT_BYTES)
+                    .map(|chunk| {
+                        let value = u128::from_le_bytes(chunk.try_into().unwrap());
+                        from_compact(value, self.field)
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Memory use of a prover run: the column data it kept in memory, and the
+/// peak resident set size of the whole process where the OS reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryReport {
+    pub stored_bytes: usize,
+    pub peak_resident_bytes: Option<usize>,
+}
+
+impl MemoryReport {
+    pub fn new(stored_bytes: usize) -> Self {
+        MemoryReport {
+            stored_bytes,
+            peak_resident_bytes: peak_resident_bytes(),
+        }
+    }
+}
+
+/// High water mark of the resident set size, which only Linux reports.
+pub fn peak_resident_bytes() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: usize = line
+        .trim_start_matches("VmHWM:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+
+    Some(kilobytes * 1024)
+}
+
+fn to_compact(element: &FieldElement) -> u128 {
+    let U256(words) = element.value();
+    (words[1] as u128) << 64 | words[0] as u128
+}
+
+fn from_compact(value: u128, field: Field) -> FieldElement {
+    FieldElement::new(U256([value as u64, (value >> 64) as u64, 0, 0]), field)
+}
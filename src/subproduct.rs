@@ -0,0 +1,191 @@
+use crate::{algebra::FieldElement, parallel::*, unipolynomial::UPolynomial};
+
+/// Nodes covering at most this many points are evaluated directly instead of
+/// being reduced further down the tree.
+const DIRECT_EVALUATION_CUTOFF: usize = 16;
+
+/// Binary tree of products of the linear factors `(X - x_i)` over an arbitrary
+/// domain. Layer `0` holds the linear factors and every node in layer `k + 1`
+/// is the product of its (at most two) children in layer `k`, so the root is
+/// the zerofier of the whole domain.
+///
+/// Building the tree costs `O(M(n) log n)` and the same tree is reused for
+/// multipoint evaluation and interpolation, where `M(n)` is the cost of
+/// multiplying two degree `n` polynomials.
+#[derive(Debug, Clone)]
+pub struct SubproductTree {
+    domain: Vec<FieldElement>,
+    layers: Vec<Vec<UPolynomial>>,
+}
+
+impl SubproductTree {
+    pub fn new(domain: &[FieldElement]) -> Self {
+        assert!(
+            !domain.is_empty(),
+            "can't build a subproduct tree over an empty domain"
+        );
+
+        let field = domain[0].field;
+        let leaves: Vec<UPolynomial> = domain
+            .iter()
+            .map(|d| UPolynomial::new(vec![-*d, field.one()]))
+            .collect();
+
+        let mut layers = vec![leaves];
+        while layers[layers.len() - 1].len() > 1 {
+            let parents = chunks!(layers[layers.len() - 1], 2)
+                .map(|pair| match pair {
+                    [left, right] => left.clone() * right.clone(),
+                    _ => pair[0].clone(),
+                })
+                .collect();
+            layers.push(parents);
+        }
+
+        SubproductTree {
+            domain: domain.to_vec(),
+            layers,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn domain(&self) -> &[FieldElement] {
+        &self.domain
+    }
+
+    /// Returns the polynomial vanishing on every point of the domain.
+    pub fn zerofier(&self) -> UPolynomial {
+        self.layers[self.layers.len() - 1][0].clone()
+    }
+
+    /// Evaluates `polynomial` on every point of the domain by reducing it
+    /// modulo the nodes of the tree on the way down.
+    pub fn evaluate(&self, polynomial: &UPolynomial) -> Vec<FieldElement> {
+        let field = self.domain[0].field;
+        let mut values = vec![field.zero(); self.domain.len()];
+        let root = self.layers.len() - 1;
+        let remainder = polynomial.modulo(&self.layers[root][0]);
+        self.evaluate_node(root, 0, remainder, &mut values);
+
+        values
+    }
+
+    fn evaluate_node(
+        &self,
+        layer: usize,
+        index: usize,
+        polynomial: UPolynomial,
+        values: &mut [FieldElement],
+    ) {
+        let start = index << layer;
+        let end = ((index + 1) << layer).min(self.domain.len());
+        if layer == 0 || end - start <= DIRECT_EVALUATION_CUTOFF {
+            for (v, d) in values[start..end].iter_mut().zip(&self.domain[start..end]) {
+                *v = polynomial.evaluate(d);
+            }
+            return;
+        }
+
+        let children = &self.layers[layer - 1];
+        for child in [2 * index, 2 * index + 1] {
+            if child < children.len() {
+                let remainder = polynomial.modulo(&children[child]);
+                self.evaluate_node(layer - 1, child, remainder, values);
+            }
+        }
+    }
+
+    /// Returns the unique polynomial of degree less than the domain size that
+    /// takes `values[i]` at the `i`th domain point.
+    pub fn interpolate(&self, values: &[FieldElement]) -> UPolynomial {
+        assert!(
+            values.len() == self.domain.len(),
+            "domain and values not the same length"
+        );
+
+        // The Lagrange weights are `values[i] / Z'(x_i)`; they only have to be
+        // recombined up the tree, every node contributing the cofactor of its
+        // sibling.
+        let derivatives = self.evaluate(&self.zerofier().derivative());
+        let mut acc: Vec<UPolynomial> = values
+            .iter()
+            .zip(derivatives)
+            .map(|(v, d)| {
+                assert!(!d.is_zero(), "domain points have to be distinct");
+                UPolynomial::new(vec![*v / d])
+            })
+            .collect();
+
+        for layer in &self.layers[..self.layers.len() - 1] {
+            acc = chunks!(acc, 2)
+                .zip(chunks!(layer, 2))
+                .map(|(sums, factors)| match (sums, factors) {
+                    ([left, right], [left_factor, right_factor]) => {
+                        left.clone() * right_factor.clone() + right.clone() * left_factor.clone()
+                    }
+                    _ => sums[0].clone(),
+                })
+                .collect();
+        }
+
+        acc.swap_remove(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// Uneven sizes, on both sides of the direct evaluation cutoff.
+    const SIZES: [usize; 6] = [1, 2, 3, 17, 33, 40];
+
+    /// Lagrange's formula term by term, `Σ v_i · Π_{j≠i} (X - x_j) / (x_i - x_j)`.
+    fn lagrange(domain: &[FieldElement], values: &[FieldElement]) -> UPolynomial {
+        let mut acc = UPolynomial::new(Vec::new());
+        for (i, (x, v)) in domain.iter().zip(values).enumerate() {
+            let mut basis = UPolynomial::new(vec![*v]);
+            for (j, other) in domain.iter().enumerate() {
+                if i != j {
+                    let scale = (*x - *other).inverse();
+                    basis = basis * UPolynomial::new(vec![-*other * scale, scale]);
+                }
+            }
+            acc = acc + basis;
+        }
+
+        acc
+    }
+
+    #[test]
+    fn evaluation_matches_naive_evaluation() {
+        let mut rng = testing::rng(26);
+        for size in SIZES {
+            let domain = rng.distinct_elements(size);
+            let tree = SubproductTree::new(&domain);
+            for degree in [0, size / 2, size + 5] {
+                let polynomial = rng.polynomial(degree);
+                let expected: Vec<FieldElement> =
+                    domain.iter().map(|x| polynomial.evaluate(x)).collect();
+                assert_eq!(tree.evaluate(&polynomial), expected);
+            }
+
+            let zerofier = tree.zerofier();
+            assert_eq!(zerofier.degree(), size as i128);
+            assert!(domain.iter().all(|x| zerofier.evaluate(x).is_zero()));
+        }
+    }
+
+    #[test]
+    fn interpolation_matches_lagrange() {
+        let mut rng = testing::rng(27);
+        for size in SIZES {
+            let domain = rng.distinct_elements(size);
+            let values = rng.elements(size);
+            let interpolant = SubproductTree::new(&domain).interpolate(&values);
+
+            assert_eq!(interpolant, lagrange(&domain, &values));
+            assert!(interpolant.degree() < size as i128);
+        }
+    }
+}
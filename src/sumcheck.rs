@@ -0,0 +1,293 @@
+use crate::{
+    algebra::FieldElement,
+    hash::Hasher,
+    multilinear::MultilinearExtension,
+    proof_stream::{sample_weights, ProofObject, ProofStream, SAMPLE_BYTES},
+};
+
+/// What a sumcheck over `Π_k f_k` reduces to: the claimed `sum` over the
+/// hypercube holds if the product of the factors at `point` equals `value`.
+/// Checking that last evaluation is left to the caller, who usually has it
+/// from a commitment or from another protocol such as GKR.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SumcheckClaim {
+    pub sum: FieldElement,
+    pub point: Vec<FieldElement>,
+    pub value: FieldElement,
+}
+
+/// Prover side of the sumcheck protocol for the sum of a product of
+/// multilinear extensions over the boolean hypercube.
+///
+/// Every round sends the univariate restriction of the product to the first
+/// free variable as its evaluations at `0, 1, ..., d` for `d` factors, and
+/// then fixes that variable to the verifier's challenge.
+#[derive(Debug, Clone)]
+pub struct SumcheckProver {
+    factors: Vec<MultilinearExtension>,
+}
+
+#[allow(dead_code)]
+impl SumcheckProver {
+    pub fn new(factors: Vec<MultilinearExtension>) -> Self {
+        assert!(!factors.is_empty(), "need at least one factor");
+        assert!(
+            factors
+                .iter()
+                .all(|f| f.num_variables() == factors[0].num_variables()),
+            "factors need the same number of variables"
+        );
+
+        SumcheckProver { factors }
+    }
+
+    /// Number of variables not fixed yet, i.e. rounds left.
+    pub fn num_variables(&self) -> usize {
+        self.factors[0].num_variables()
+    }
+
+    pub fn degree(&self) -> usize {
+        self.factors.len()
+    }
+
+    pub fn sum(&self) -> FieldElement {
+        let values = self.factors[0].values();
+        (0..values.len()).fold(values[0].field.zero(), |acc, i| {
+            acc + self
+                .factors
+                .iter()
+                .fold(values[0].field.one(), |p, f| p * f.values()[i])
+        })
+    }
+
+    /// Evaluations of this round's univariate polynomial at `0, 1, ..., d`.
+    pub fn round_polynomial(&self) -> Vec<FieldElement> {
+        assert!(self.num_variables() > 0, "sumcheck is already finished");
+        let field = self.factors[0].values()[0].field;
+        let half = self.factors[0].values().len() / 2;
+
+        let mut evaluations = vec![field.zero(); self.degree() + 1];
+        for i in 0..half {
+            for (t, evaluation) in evaluations.iter_mut().enumerate() {
+                let t = FieldElement::from_u64(t as u64, field);
+                *evaluation += self.factors.iter().fold(field.one(), |acc, f| {
+                    let (lo, hi) = (f.values()[i], f.values()[i + half]);
+                    acc * (lo + t * (hi - lo))
+                });
+            }
+        }
+
+        evaluations
+    }
+
+    /// Fixes the current variable to the verifier's challenge.
+    pub fn fix(&mut self, challenge: &FieldElement) {
+        for f in self.factors.iter_mut() {
+            *f = f.fix_first_variable(challenge);
+        }
+    }
+
+    /// Value of the product once every variable is fixed.
+    pub fn final_value(&self) -> FieldElement {
+        assert!(self.num_variables() == 0, "sumcheck isn't finished yet");
+        let field = self.factors[0].values()[0].field;
+        self.factors
+            .iter()
+            .fold(field.one(), |acc, f| acc * f.values()[0])
+    }
+
+    /// Non-interactive sumcheck: sends the sum and every round polynomial,
+    /// drawing the challenges from the transcript.
+    pub fn prove<H: Hasher>(mut self, proof_stream: &mut ProofStream<H>) -> SumcheckClaim {
+        let field = self.factors[0].values()[0].field;
+        let sum = self.sum();
+        proof_stream.push(ProofObject::FieldElements(vec![sum]));
+
+        let mut point = Vec::with_capacity(self.num_variables());
+        while self.num_variables() > 0 {
+            proof_stream.push(ProofObject::FieldElements(self.round_polynomial()));
+            let bytes = proof_stream.prover_fiat_shamir(SAMPLE_BYTES);
+            let challenge = sample_weights(&bytes, 1, field)[0];
+            self.fix(&challenge);
+            point.push(challenge);
+        }
+
+        SumcheckClaim {
+            sum,
+            point,
+            value: self.final_value(),
+        }
+    }
+}
+
+/// Verifier side of the sumcheck protocol, fed one round polynomial and one
+/// challenge at a time.
+#[derive(Debug, Clone)]
+pub struct SumcheckVerifier {
+    num_variables: usize,
+    degree: usize,
+    sum: FieldElement,
+    claim: FieldElement,
+    point: Vec<FieldElement>,
+}
+
+#[allow(dead_code)]
+impl SumcheckVerifier {
+    pub fn new(num_variables: usize, degree: usize, sum: FieldElement) -> Self {
+        assert!(degree > 0, "need at least one factor");
+        SumcheckVerifier {
+            num_variables,
+            degree,
+            sum,
+            claim: sum,
+            point: Vec::with_capacity(num_variables),
+        }
+    }
+
+    /// Checks a round polynomial against the running claim and moves the
+    /// claim to its value at `challenge`.
+    pub fn receive(&mut self, evaluations: &[FieldElement], challenge: FieldElement) -> bool {
+        if self.point.len() == self.num_variables
+            || evaluations.len() != self.degree + 1
+            || evaluations[0] + evaluations[1] != self.claim
+        {
+            return false;
+        }
+
+        self.claim = interpolate_at(evaluations, &challenge);
+        self.point.push(challenge);
+        true
+    }
+
+    /// The claim left after the last round, or `None` if rounds are missing.
+    pub fn finish(self) -> Option<SumcheckClaim> {
+        (self.point.len() == self.num_variables).then_some(SumcheckClaim {
+            sum: self.sum,
+            point: self.point,
+            value: self.claim,
+        })
+    }
+
+    /// Replays a non-interactive sumcheck of `num_variables` rounds over a
+    /// product of `degree` factors. The returned claim still has to be checked
+    /// against the factors at `point`.
+    pub fn verify<H: Hasher>(
+        proof_stream: &mut ProofStream<H>,
+        num_variables: usize,
+        degree: usize,
+    ) -> Option<SumcheckClaim> {
+        let sum = *proof_stream.pull()?.into_field_elements()?.first()?;
+        let mut verifier = SumcheckVerifier::new(num_variables, degree, sum);
+
+        for _ in 0..num_variables {
+            let evaluations = proof_stream.pull()?.into_field_elements()?;
+            let bytes = proof_stream.verifier_fiat_shamir(SAMPLE_BYTES);
+            let challenge = sample_weights(&bytes, 1, sum.field)[0];
+            if !verifier.receive(&evaluations, challenge) {
+                return None;
+            }
+        }
+
+        verifier.finish()
+    }
+}
+
+/// Value at `x` of the polynomial taking `evaluations[i]` at `i`.
+fn interpolate_at(evaluations: &[FieldElement], x: &FieldElement) -> FieldElement {
+    let field = x.field;
+    let node = |i: usize| FieldElement::from_u64(i as u64, field);
+
+    evaluations
+        .iter()
+        .enumerate()
+        .fold(field.zero(), |acc, (i, y)| {
+            let (numerator, denominator) = (0..evaluations.len())
+                .filter(|j| *j != i)
+                .fold((field.one(), field.one()), |(n, d), j| {
+                    (n * (*x - node(j)), d * (node(i) - node(j)))
+                });
+            acc + *y * numerator / denominator
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{algebra::Field, hash::Sha3, testing};
+
+    const NUM_VARIABLES: usize = 4;
+
+    fn factors(degree: usize) -> Vec<MultilinearExtension> {
+        let mut rng = testing::rng(42);
+        (0..degree)
+            .map(|_| MultilinearExtension::new(rng.elements(1 << NUM_VARIABLES)))
+            .collect()
+    }
+
+    fn product_at(factors: &[MultilinearExtension], point: &[FieldElement]) -> FieldElement {
+        factors
+            .iter()
+            .fold(point[0].field.one(), |acc, f| acc * f.evaluate(point))
+    }
+
+    #[test]
+    fn honest_proofs_verify() {
+        for degree in 1..4 {
+            let factors = factors(degree);
+            let mut proof_stream = ProofStream::<Sha3>::new();
+            let claim = SumcheckProver::new(factors.clone()).prove(&mut proof_stream);
+
+            let verified =
+                SumcheckVerifier::verify(&mut proof_stream, NUM_VARIABLES, degree).unwrap();
+            assert_eq!(verified, claim);
+            assert_eq!(verified.value, product_at(&factors, &verified.point));
+        }
+    }
+
+    #[test]
+    fn wrong_sums_are_caught() {
+        let factors = factors(2);
+        let field = Field::new();
+        let mut prover = SumcheckProver::new(factors.clone());
+        let wrong_sum = prover.sum() + field.one();
+
+        // Sent as is, the first round polynomial contradicts the claimed sum.
+        let mut verifier = SumcheckVerifier::new(NUM_VARIABLES, 2, wrong_sum);
+        assert!(!verifier.receive(&prover.round_polynomial(), field.one()));
+
+        // Shifting every round polynomial by half the error of the running
+        // claim gets past the rounds, but the error is still there, halved
+        // once per round, when the factors get evaluated.
+        let mut rng = testing::rng(43);
+        let mut verifier = SumcheckVerifier::new(NUM_VARIABLES, 2, wrong_sum);
+        let half = FieldElement::from_u64(2, field).inverse();
+        let mut error = field.one();
+        for _ in 0..NUM_VARIABLES {
+            error *= half;
+            let mut evaluations = prover.round_polynomial();
+            for evaluation in evaluations.iter_mut() {
+                *evaluation += error;
+            }
+
+            let challenge = rng.element();
+            assert!(verifier.receive(&evaluations, challenge));
+            prover.fix(&challenge);
+        }
+        let claim = verifier.finish().unwrap();
+        assert_eq!(claim.value, product_at(&factors, &claim.point) + error);
+    }
+
+    #[test]
+    fn malformed_proofs_are_rejected() {
+        let factors = factors(2);
+        let mut proof_stream = ProofStream::<Sha3>::new();
+        SumcheckProver::new(factors).prove(&mut proof_stream);
+
+        // Too many rounds run out of proof, the wrong degree doesn't match the
+        // round polynomials.
+        assert!(
+            SumcheckVerifier::verify(&mut proof_stream.clone(), NUM_VARIABLES + 1, 2).is_none()
+        );
+        assert!(SumcheckVerifier::verify(&mut proof_stream.clone(), NUM_VARIABLES, 3).is_none());
+    }
+}
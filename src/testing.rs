@@ -0,0 +1,22 @@
+// Seeded inputs shared by the tests, so that every failure reproduces.
+
+use crate::{
+    algebra::{Field, FieldElement},
+    random::FieldRng,
+};
+
+pub fn rng(seed: u64) -> FieldRng {
+    FieldRng::seed_from_u64(seed, Field::new())
+}
+
+/// Mostly uniform elements, with zero, one and minus one mixed in since
+/// that's where reduction bugs hide.
+pub fn element(rng: &mut FieldRng) -> FieldElement {
+    let field = rng.field();
+    match rng.below(8) {
+        0 => field.zero(),
+        1 => field.one(),
+        2 => -field.one(),
+        _ => rng.element(),
+    }
+}
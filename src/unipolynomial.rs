@@ -1,9 +1,31 @@
-use crate::algebra::FieldElement;
+use bigint::U256;
+
+use crate::{
+    algebra::FieldElement,
+    ntt::{intt, ntt},
+    parallel::*,
+    subproduct::SubproductTree,
+};
 use std::{
     cmp::max,
     ops::{self, Add},
 };
 
+/// Below this many points (or this degree) evaluating point by point beats
+/// building a `SubproductTree`.
+const SUBPRODUCT_TREE_THRESHOLD: usize = 64;
+
+/// Operands shorter than this are multiplied schoolbook.
+const KARATSUBA_THRESHOLD: usize = 32;
+
+/// Operands at least this long are multiplied through an NTT, provided the
+/// field has a large enough power-of-two root of unity.
+const NTT_THRESHOLD: usize = 128;
+
+/// Quotients and divisors at least this long are divided through a Newton
+/// iteration instead of long division.
+const NEWTON_DIVISION_THRESHOLD: usize = 64;
+
 // TODO: Implement the copy trait to get rid of the `clone()` calls
 #[derive(Debug, Clone)]
 pub struct UPolynomial {
@@ -35,12 +57,12 @@ impl ops::Add for UPolynomial {
                     .map(|_| field.zero())
                     .collect();
 
-            for i in 0..self.coefficients.len() {
-                acc[i] = acc[i] + self.coefficients[i];
+            for (a, c) in acc.iter_mut().zip(&self.coefficients) {
+                *a += *c;
             }
 
-            for i in 0..rhs.coefficients.len() {
-                acc[i] = acc[i] + rhs.coefficients[i];
+            for (a, c) in acc.iter_mut().zip(&rhs.coefficients) {
+                *a += *c;
             }
 
             UPolynomial { coefficients: acc }
@@ -60,28 +82,8 @@ impl ops::Mul for UPolynomial {
     type Output = UPolynomial;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        if self.coefficients.is_empty() || rhs.coefficients.is_empty() {
-            UPolynomial {
-                coefficients: Vec::new(),
-            }
-        } else {
-            let zero = self.coefficients[0].field.zero();
-            let mut buf: Vec<FieldElement> =
-                (0..(self.coefficients.len() + rhs.coefficients.len() - 1))
-                    .map(|_| zero)
-                    .collect();
-
-            for i in 0..self.coefficients.len() {
-                if self.coefficients[i].is_zero() {
-                    continue;
-                } else {
-                    for j in 0..rhs.coefficients.len() {
-                        buf[i + j] = buf[i + j] + self.coefficients[i] + rhs.coefficients[j];
-                    }
-                }
-            }
-
-            UPolynomial { coefficients: buf }
+        UPolynomial {
+            coefficients: multiply(&self.coefficients, &rhs.coefficients),
         }
     }
 }
@@ -96,28 +98,36 @@ impl ops::Div for UPolynomial {
     }
 }
 
+/// Trailing zero coefficients don't change the polynomial, so they're ignored.
 impl PartialEq for UPolynomial {
     fn eq(&self, rhs: &Self) -> bool {
-        self.coefficients == rhs.coefficients
-    }
-
-    fn ne(&self, rhs: &Self) -> bool {
-        !self.eq(rhs)
+        let len = (self.degree() + 1) as usize;
+        len == (rhs.degree() + 1) as usize && self.coefficients[..len] == rhs.coefficients[..len]
     }
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 enum PolynomialError {
     DivByZero(String),
 }
 
+#[allow(dead_code)]
 impl UPolynomial {
     pub fn new(coefficients: Vec<FieldElement>) -> Self {
         UPolynomial { coefficients }
     }
 
+    pub fn coefficients(&self) -> &[FieldElement] {
+        &self.coefficients
+    }
+
     /// Returns the index of where the last non zero `FieldElement` is.
     pub fn degree(&self) -> i128 {
+        if self.coefficients.is_empty() {
+            return -1;
+        }
+
         let zero = self.coefficients[0].field.zero();
         let non_zero_coeff_cnt = self
             .coefficients
@@ -141,11 +151,7 @@ impl UPolynomial {
     }
 
     pub fn is_zero(&self) -> bool {
-        if self.degree() == -1 {
-            true
-        } else {
-            false
-        }
+        self.degree() == -1
     }
 
     pub fn leading_coefficient(&self) -> FieldElement {
@@ -170,33 +176,23 @@ impl UPolynomial {
         } else if numerator.degree() < denominator.degree() {
             Ok((UPolynomial::new(Vec::new()), numerator.clone()))
         } else {
-            let field = denominator.coefficients[0].field;
-            let mut remainder = numerator.clone();
-            let mut quotient_coefficients: Vec<FieldElement> =
-                (0..numerator.degree() - denominator.degree() + 1)
-                    .map(|_| field.zero())
-                    .collect();
-            for _ in 0..numerator.degree() - denominator.degree() + 1 {
-                if remainder.degree() < denominator.degree() {
-                    break;
-                } else {
-                    let coefficient =
-                        remainder.leading_coefficient() / denominator.leading_coefficient();
-                    let shift: usize = (remainder.degree() - denominator.degree())
-                        .try_into()
-                        .unwrap();
-                    let mut sub_coeff = (0..shift)
-                        .map(|_| field.zero())
-                        .collect::<Vec<FieldElement>>();
-                    sub_coeff.push(coefficient);
-                    let subtractee: UPolynomial = UPolynomial::new(sub_coeff) * (denominator.clone());
-                    quotient_coefficients[shift] = coefficient;
-                    remainder = remainder - subtractee;
-                }
-            }
-
-            let quotient = UPolynomial::new(quotient_coefficients);
-            Ok((quotient, remainder))
+            let numerator = &numerator.coefficients[..=numerator.degree() as usize];
+            let denominator = &denominator.coefficients[..=denominator.degree() as usize];
+            let quotient_len = numerator.len() - denominator.len() + 1;
+
+            let quotient = if quotient_len.min(denominator.len()) >= NEWTON_DIVISION_THRESHOLD {
+                newton_quotient(numerator, denominator)
+            } else {
+                long_division_quotient(numerator, denominator)
+            };
+            let remainder = numerator
+                .iter()
+                .zip(multiply(denominator, &quotient))
+                .take(denominator.len() - 1)
+                .map(|(n, p)| *n - p)
+                .collect();
+
+            Ok((UPolynomial::new(quotient), UPolynomial::new(remainder)))
         }
     }
 
@@ -206,92 +202,109 @@ impl UPolynomial {
     }
 
     pub fn modexp(&self, exponent: i128) -> Self {
+        assert!(
+            exponent >= 0,
+            "can't raise a polynomial to a negative power"
+        );
         if self.is_zero() {
-            UPolynomial::new(Vec::new())
-        } else if exponent == 0 {
-            UPolynomial::new(vec![self.coefficients[0].field.one()])
-        } else {
-            let mut acc = UPolynomial::new(vec![self.coefficients[0].field.one()]);
-            let binary_str = format!("{:b}", exponent);
-            for i in (0..binary_str.len() - 2).rev() {
-                let tmp = acc.clone();
-                acc = acc * tmp;
-                if (1 << i) & exponent != 0 {
-                    acc = acc * (self.clone());
-                }
-            }
+            return UPolynomial::new(Vec::new());
+        }
 
-            acc
+        let mut acc = UPolynomial::new(vec![self.coefficients[0].field.one()]);
+        for i in (0..i128::BITS - exponent.leading_zeros()).rev() {
+            acc = acc.clone() * acc;
+            if (exponent >> i) & 1 == 1 {
+                acc = acc * self.clone();
+            }
         }
+
+        acc
     }
 
     pub fn evaluate(&self, point: &FieldElement) -> FieldElement {
-        let mut xi = point.field.one();
         let mut value = point.field.zero();
-        for c in &self.coefficients {
-            value += *c * xi; // bad paradigm of dereferencing?
-            xi *= *point; // bad paradigm of dereferencing?
+        for c in self.coefficients.iter().rev() {
+            value = value * *point + *c;
         }
 
         value
     }
 
-    pub fn evaluate_domain(&self, domain: &Vec<FieldElement>) -> Vec<FieldElement> {
-        let mut res = Vec::new();
-        for p in domain {
-            let val = self.evaluate(p);
-            res.push(val);
+    /// Evaluates on every point of `domain`, going through a `SubproductTree`
+    /// once both the polynomial and the domain are large enough for it to pay
+    /// off.
+    pub fn evaluate_domain(&self, domain: &[FieldElement]) -> Vec<FieldElement> {
+        if domain.len() < SUBPRODUCT_TREE_THRESHOLD
+            || self.degree() < SUBPRODUCT_TREE_THRESHOLD as i128
+        {
+            iter!(domain).map(|p| self.evaluate(p)).collect()
+        } else {
+            SubproductTree::new(domain).evaluate(self)
         }
-
-        res
     }
 
-    pub fn interpolate_domain(
-        &self,
-        domain: &Vec<FieldElement>,
-        values: &Vec<FieldElement>,
-    ) -> Self {
+    pub fn interpolate_domain(&self, domain: &[FieldElement], values: &[FieldElement]) -> Self {
         assert!(
             domain.len() == values.len(),
             "domain and values not the same length big bro"
         );
         assert!(
-            domain.len() > 0,
+            !domain.is_empty(),
             "can't interpolate between two values big bro"
         );
 
-        let field = domain[0].field;
-        let x = UPolynomial::new(vec![field.zero(), field.one()]);
-        let mut acc = UPolynomial::new(Vec::new());
-        for i in 0..domain.len() {
-            let mut prod = UPolynomial::new(vec![values[i]]);
-            for j in 0..domain.len() {
-                if j == i {
-                    continue;
-                } else {
-                    let poly_a = UPolynomial::new(vec![domain[j]]);
-                    let poly_b = UPolynomial::new(vec![(domain[i] - domain[j]).inverse()]);
-                    prod = prod * ((x.clone() - poly_a) * poly_b); // bad paradigm for sureski
-                }
-            }
-            acc = acc + prod;
-        }
+        SubproductTree::new(domain).interpolate(values)
+    }
 
-        acc
+    /// Interpolates `values` over `domain` and, on top of that, the random
+    /// `randomizers` over `randomizer_points`, which must lie outside
+    /// `domain`. The result still agrees with `values` on `domain` but its
+    /// degree grows by one per randomizer, so that many evaluations elsewhere
+    /// reveal nothing about `values`.
+    pub fn interpolate_randomized(
+        &self,
+        domain: &[FieldElement],
+        values: &[FieldElement],
+        randomizer_points: &[FieldElement],
+        randomizers: &[FieldElement],
+    ) -> Self {
+        assert!(
+            randomizer_points.len() == randomizers.len(),
+            "randomizer points and randomizers not the same length"
+        );
+        assert!(
+            randomizer_points.iter().all(|p| !domain.contains(p)),
+            "randomizer points have to lie outside the domain"
+        );
+
+        self.interpolate_domain(
+            &[domain, randomizer_points].concat(),
+            &[values, randomizers].concat(),
+        )
+    }
+
+    pub fn zeroifier_domain(&self, domain: &[FieldElement]) -> Self {
+        SubproductTree::new(domain).zerofier()
     }
 
-    pub fn zeroifier_domain(&self, domain: &Vec<FieldElement>) -> Self {
-        let field = domain[0].field;
-        let x = UPolynomial::new(vec![field.zero(), field.one()]);
-        let mut acc = UPolynomial::new(vec![field.one()]);
-        for d in domain {
-            acc = acc
-                * (x.clone()
-                    - UPolynomial {
-                        coefficients: vec![*d],
-                    }); // bad paradigm for sureski
+    /// Returns the formal derivative.
+    pub fn derivative(&self) -> Self {
+        let mut factor = match self.coefficients.first() {
+            Some(c) => c.field.zero(),
+            None => return UPolynomial::new(Vec::new()),
+        };
+
+        UPolynomial {
+            coefficients: self
+                .coefficients
+                .iter()
+                .skip(1)
+                .map(|c| {
+                    factor += c.field.one();
+                    factor * *c
+                })
+                .collect(),
         }
-        acc
     }
 
     pub fn scale(&self, factor: &FieldElement) -> Self {
@@ -302,14 +315,443 @@ impl UPolynomial {
         }
     }
 
-    pub fn test_colinearity(&self, points: &Vec<(FieldElement, FieldElement)>) -> bool {
-        let (mut domain, mut values) = (Vec::new(), Vec::new());
-        let _ = points.iter().map(|pair| {
-            domain.push(pair.0);
-            values.push(pair.1);
-        });
+    pub fn test_colinearity(&self, points: &[(FieldElement, FieldElement)]) -> bool {
+        let (domain, values): (Vec<FieldElement>, Vec<FieldElement>) =
+            points.iter().copied().unzip();
 
         let polynomial = self.interpolate_domain(&domain, &values);
         polynomial.degree() <= 1
     }
 }
+
+/// Multiplies two coefficient vectors, picking schoolbook, Karatsuba or
+/// NTT-based multiplication by operand size.
+fn multiply(lhs: &[FieldElement], rhs: &[FieldElement]) -> Vec<FieldElement> {
+    if lhs.is_empty() || rhs.is_empty() {
+        return Vec::new();
+    }
+
+    let shortest = lhs.len().min(rhs.len());
+    if shortest < KARATSUBA_THRESHOLD {
+        schoolbook(lhs, rhs)
+    } else if shortest >= NTT_THRESHOLD {
+        ntt_multiply(lhs, rhs).unwrap_or_else(|| karatsuba(lhs, rhs))
+    } else {
+        karatsuba(lhs, rhs)
+    }
+}
+
+fn schoolbook(lhs: &[FieldElement], rhs: &[FieldElement]) -> Vec<FieldElement> {
+    let field = lhs[0].field;
+    let mut product = vec![field.zero(); lhs.len() + rhs.len() - 1];
+    for (i, a) in lhs.iter().enumerate() {
+        if a.is_zero() {
+            continue;
+        }
+        for (p, b) in product[i..].iter_mut().zip(rhs) {
+            *p += *a * *b;
+        }
+    }
+
+    product
+}
+
+fn karatsuba(lhs: &[FieldElement], rhs: &[FieldElement]) -> Vec<FieldElement> {
+    if lhs.len().min(rhs.len()) < KARATSUBA_THRESHOLD {
+        return schoolbook(lhs, rhs);
+    }
+
+    let field = lhs[0].field;
+    let half = lhs.len().max(rhs.len()).div_ceil(2);
+    let (l0, l1) = lhs.split_at(half.min(lhs.len()));
+    let (r0, r1) = rhs.split_at(half.min(rhs.len()));
+    let mut product = vec![field.zero(); lhs.len() + rhs.len() - 1];
+
+    if l1.is_empty() || r1.is_empty() {
+        // Unbalanced operands, only the longer one gets split.
+        let (short, long0, long1) = if l1.is_empty() {
+            (lhs, r0, r1)
+        } else {
+            (rhs, l0, l1)
+        };
+        add_into(&mut product, 0, &karatsuba(short, long0));
+        add_into(&mut product, half, &karatsuba(short, long1));
+        return product;
+    }
+
+    // (l0 + l1·X^h)(r0 + r1·X^h) = z0 + (z1 - z0 - z2)·X^h + z2·X^2h
+    let z0 = karatsuba(l0, r0);
+    let z2 = karatsuba(l1, r1);
+    let mut z1 = karatsuba(&add_slices(l0, l1), &add_slices(r0, r1));
+    for (i, c) in z0.iter().enumerate() {
+        z1[i] = z1[i] - *c;
+    }
+    for (i, c) in z2.iter().enumerate() {
+        z1[i] = z1[i] - *c;
+    }
+
+    add_into(&mut product, 0, &z0);
+    add_into(&mut product, half, &z1);
+    add_into(&mut product, 2 * half, &z2);
+
+    product
+}
+
+/// Multiplies through an NTT of the next power of two above the product
+/// length, or returns `None` if the field has no root of unity of that order.
+fn ntt_multiply(lhs: &[FieldElement], rhs: &[FieldElement]) -> Option<Vec<FieldElement>> {
+    let field = lhs[0].field;
+    let len = lhs.len() + rhs.len() - 1;
+    let size = len.next_power_of_two();
+    let root = field.primite_nth_root(&U256::from(size as u64)).ok()?;
+
+    let mut lhs = lhs.to_vec();
+    let mut rhs = rhs.to_vec();
+    lhs.resize(size, field.zero());
+    rhs.resize(size, field.zero());
+
+    let values: Vec<FieldElement> = ntt(&lhs, &root)
+        .into_iter()
+        .zip(ntt(&rhs, &root))
+        .map(|(a, b)| a * b)
+        .collect();
+    let mut product = intt(&values, &root);
+    product.truncate(len);
+
+    Some(product)
+}
+
+fn add_slices(lhs: &[FieldElement], rhs: &[FieldElement]) -> Vec<FieldElement> {
+    let (long, short) = if lhs.len() >= rhs.len() {
+        (lhs, rhs)
+    } else {
+        (rhs, lhs)
+    };
+    let mut sum = long.to_vec();
+    add_into(&mut sum, 0, short);
+
+    sum
+}
+
+fn add_into(acc: &mut [FieldElement], offset: usize, terms: &[FieldElement]) {
+    for (a, t) in acc[offset..].iter_mut().zip(terms) {
+        *a += *t;
+    }
+}
+
+/// Quotient of schoolbook long division, `denominator` being trimmed so that
+/// its last coefficient is non zero.
+fn long_division_quotient(
+    numerator: &[FieldElement],
+    denominator: &[FieldElement],
+) -> Vec<FieldElement> {
+    let field = denominator[0].field;
+    let m = denominator.len() - 1;
+    let lead_inverse = denominator[m].inverse();
+    let mut remainder = numerator.to_vec();
+    let mut quotient = vec![field.zero(); numerator.len() - m];
+
+    for i in (0..quotient.len()).rev() {
+        let coefficient = remainder[i + m] * lead_inverse;
+        quotient[i] = coefficient;
+        if coefficient.is_zero() {
+            continue;
+        }
+        for (r, d) in remainder[i..=i + m].iter_mut().zip(denominator) {
+            *r = *r - coefficient * *d;
+        }
+    }
+
+    quotient
+}
+
+/// Quotient through the reversed polynomials: with `k` the quotient length,
+/// `rev(q) = rev(n) · rev(d)^-1 mod X^k`, where the power series inverse comes
+/// from a Newton iteration.
+fn newton_quotient(numerator: &[FieldElement], denominator: &[FieldElement]) -> Vec<FieldElement> {
+    let field = denominator[0].field;
+    let k = numerator.len() - denominator.len() + 1;
+    let reversed_numerator: Vec<FieldElement> = numerator.iter().rev().take(k).copied().collect();
+    let reversed_denominator: Vec<FieldElement> = denominator.iter().rev().copied().collect();
+
+    let inverse = power_series_inverse(&reversed_denominator, k);
+    let mut quotient = multiply(&reversed_numerator, &inverse);
+    quotient.resize(k, field.zero());
+    quotient.reverse();
+
+    quotient
+}
+
+/// Returns `g` with `f·g = 1 mod X^precision`, doubling the precision of
+/// `g <- g·(2 - f·g)` on every step.
+fn power_series_inverse(f: &[FieldElement], precision: usize) -> Vec<FieldElement> {
+    let field = f[0].field;
+    let two = field.one() + field.one();
+    let mut inverse = vec![f[0].inverse()];
+    let mut current = 1;
+
+    while current < precision {
+        current = (2 * current).min(precision);
+        let mut error = multiply(&f[..f.len().min(current)], &inverse);
+        error.resize(current, field.zero());
+        for e in error.iter_mut() {
+            *e = -*e;
+        }
+        error[0] += two;
+
+        inverse = multiply(&inverse, &error);
+        inverse.truncate(current);
+    }
+
+    inverse
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{algebra::Field, testing};
+
+    fn field() -> Field {
+        Field::new()
+    }
+
+    /// Textbook convolution every strategy is checked against.
+    fn reference(lhs: &[FieldElement], rhs: &[FieldElement]) -> Vec<FieldElement> {
+        let field = lhs[0].field;
+        let mut product = vec![field.zero(); lhs.len() + rhs.len() - 1];
+        for i in 0..lhs.len() {
+            for j in 0..rhs.len() {
+                product[i + j] += lhs[i] * rhs[j];
+            }
+        }
+        product
+    }
+
+    const SIZES: [(usize, usize); 8] = [
+        (1, 1),
+        (3, 7),
+        (31, 33),
+        (32, 32),
+        (40, 90),
+        (65, 64),
+        (90, 31),
+        (128, 129),
+    ];
+
+    #[test]
+    fn strategies_match_reference() {
+        let mut rng = testing::rng(0x5eed);
+        for (n, m) in SIZES {
+            let lhs = rng.elements(n);
+            let rhs = rng.elements(m);
+            let expected = reference(&lhs, &rhs);
+
+            assert_eq!(schoolbook(&lhs, &rhs), expected, "schoolbook {n}x{m}");
+            assert_eq!(karatsuba(&lhs, &rhs), expected, "karatsuba {n}x{m}");
+            assert_eq!(ntt_multiply(&lhs, &rhs).unwrap(), expected, "ntt {n}x{m}");
+            assert_eq!(multiply(&lhs, &rhs), expected, "multiply {n}x{m}");
+        }
+    }
+
+    #[test]
+    fn mul_by_zero_polynomial_is_empty() {
+        let mut rng = testing::rng(7);
+        let f = UPolynomial::new(rng.elements(5));
+        assert!((f.clone() * UPolynomial::new(Vec::new())).is_zero());
+        assert!((UPolynomial::new(Vec::new()) * f).is_zero());
+    }
+
+    #[test]
+    fn ntt_round_trip() {
+        let field = field();
+        let mut rng = testing::rng(11);
+        for n in [1, 2, 8, 64] {
+            let root = field.primite_nth_root(&U256::from(n as u64)).unwrap();
+            let values = rng.elements(n);
+            let transformed = ntt(&values, &root);
+            let expected: Vec<FieldElement> = (0..n)
+                .map(|i| UPolynomial::new(values.clone()).evaluate(&root.modexp(i)))
+                .collect();
+
+            assert_eq!(transformed, expected);
+            assert_eq!(intt(&transformed, &root), values);
+        }
+    }
+
+    #[test]
+    fn division_identity() {
+        let mut rng = testing::rng(13);
+        for (n, m) in [(5, 1), (10, 3), (40, 40), (140, 64), (200, 70)] {
+            let numerator = UPolynomial::new(rng.elements(n));
+            let denominator = UPolynomial::new(rng.elements(m));
+            let (quotient, remainder) = numerator.divide(&numerator, &denominator).unwrap();
+
+            assert!(remainder.degree() < denominator.degree());
+            let recombined = quotient * denominator + remainder;
+            assert_eq!(
+                &recombined.coefficients()[..n],
+                numerator.coefficients(),
+                "{n}/{m}"
+            );
+        }
+    }
+
+    #[test]
+    fn ring_laws() {
+        let field = field();
+        let mut rng = testing::rng(17);
+        let zero = UPolynomial::new(Vec::new());
+        let one = UPolynomial::new(vec![field.one()]);
+        for _ in 0..12 {
+            let [f, g, h] = [0; 3].map(|_| {
+                let degree = rng.below(48);
+                rng.polynomial(degree)
+            });
+
+            assert_eq!(f.clone() + g.clone(), g.clone() + f.clone());
+            assert_eq!(f.clone() * g.clone(), g.clone() * f.clone());
+            assert_eq!(
+                (f.clone() + g.clone()) + h.clone(),
+                f.clone() + (g.clone() + h.clone())
+            );
+            assert_eq!(
+                (f.clone() * g.clone()) * h.clone(),
+                f.clone() * (g.clone() * h.clone())
+            );
+            assert_eq!(
+                f.clone() * (g.clone() + h.clone()),
+                f.clone() * g.clone() + f.clone() * h.clone()
+            );
+            assert_eq!(f.clone() + zero.clone(), f);
+            assert_eq!(f.clone() * one.clone(), f);
+            assert!((f.clone() - f.clone()).is_zero());
+            assert_eq!(f.clone() + g.clone() - g.clone(), f);
+            assert_eq!((f.clone() * g.clone()).degree(), f.degree() + g.degree());
+        }
+    }
+
+    #[test]
+    fn evaluation_is_a_homomorphism() {
+        let mut rng = testing::rng(19);
+        for _ in 0..50 {
+            let (m, n) = (rng.below(40), rng.below(40));
+            let f = rng.polynomial(m);
+            let g = rng.polynomial(n);
+            let x = rng.element();
+
+            assert_eq!(
+                (f.clone() + g.clone()).evaluate(&x),
+                f.evaluate(&x) + g.evaluate(&x)
+            );
+            assert_eq!(
+                (f.clone() - g.clone()).evaluate(&x),
+                f.evaluate(&x) - g.evaluate(&x)
+            );
+            assert_eq!(
+                (f.clone() * g.clone()).evaluate(&x),
+                f.evaluate(&x) * g.evaluate(&x)
+            );
+        }
+    }
+
+    #[test]
+    fn modexp_is_repeated_multiplication() {
+        let field = field();
+        let mut rng = testing::rng(23);
+        for degree in [0, 1, 3, 10] {
+            let f = rng.polynomial(degree);
+            let mut expected = UPolynomial::new(vec![field.one()]);
+            for exponent in 0..12 {
+                assert_eq!(f.modexp(exponent), expected, "degree {degree}, ^{exponent}");
+                expected = expected * f.clone();
+            }
+        }
+    }
+
+    #[test]
+    fn interpolation_round_trip() {
+        let mut rng = testing::rng(29);
+        for n in [1, 2, 7, 64, 100] {
+            let domain = rng.distinct_elements(n);
+            let values = rng.elements(n);
+            let empty = UPolynomial::new(Vec::new());
+            let polynomial = empty.interpolate_domain(&domain, &values);
+
+            assert!(polynomial.degree() < n as i128);
+            assert_eq!(polynomial.evaluate_domain(&domain), values, "{n} points");
+
+            let f = rng.polynomial(n - 1);
+            assert_eq!(
+                empty.interpolate_domain(&domain, &f.evaluate_domain(&domain)),
+                f
+            );
+        }
+    }
+
+    #[test]
+    fn randomized_interpolation_agrees_on_domain() {
+        let mut rng = testing::rng(30);
+        let empty = UPolynomial::new(Vec::new());
+        for (n, k) in [(1, 1), (8, 2), (64, 4)] {
+            let points = rng.distinct_elements(n + k);
+            let (domain, randomizer_points) = points.split_at(n);
+            let values = rng.elements(n);
+            let randomizers = rng.elements(k);
+
+            let polynomial =
+                empty.interpolate_randomized(domain, &values, randomizer_points, &randomizers);
+            assert_eq!(polynomial.evaluate_domain(domain), values);
+            assert_eq!(polynomial.evaluate_domain(randomizer_points), randomizers);
+            assert!(polynomial.degree() < (n + k) as i128);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "outside the domain")]
+    fn randomizer_points_on_the_domain_are_rejected() {
+        let mut rng = testing::rng(31);
+        let domain = rng.distinct_elements(4);
+        let values = rng.elements(4);
+        let randomizers = rng.elements(1);
+
+        UPolynomial::new(Vec::new()).interpolate_randomized(
+            &domain,
+            &values,
+            &domain[2..3],
+            &randomizers,
+        );
+    }
+
+    #[test]
+    fn zerofier_vanishes_exactly_on_domain() {
+        let field = field();
+        let mut rng = testing::rng(31);
+        for n in [1, 5, 64, 90] {
+            let points = rng.distinct_elements(n + 10);
+            let (domain, outside) = points.split_at(n);
+            let zerofier = UPolynomial::new(Vec::new()).zeroifier_domain(domain);
+
+            assert_eq!(zerofier.degree(), n as i128);
+            assert_eq!(zerofier.leading_coefficient(), field.one());
+            assert!(domain.iter().all(|x| zerofier.evaluate(x).is_zero()));
+            assert!(outside.iter().all(|x| !zerofier.evaluate(x).is_zero()));
+        }
+    }
+
+    #[test]
+    fn colinearity() {
+        let field = field();
+        let mut rng = testing::rng(37);
+        let empty = UPolynomial::new(Vec::new());
+        for _ in 0..20 {
+            let line = rng.polynomial(1);
+            let xs = rng.distinct_elements(4);
+            let mut points: Vec<(FieldElement, FieldElement)> =
+                xs.iter().map(|x| (*x, line.evaluate(x))).collect();
+            assert!(empty.test_colinearity(&points));
+
+            points[2].1 += field.one();
+            assert!(!empty.test_colinearity(&points));
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{
+    algebra::{batch_inverse, Field, FieldElement},
+    parallel::*,
+    unipolynomial::UPolynomial,
+};
+
+/// Closed form of the zerofiers a STARK divides by: `X^n - offset^n`, which
+/// vanishes on the coset `offset·⟨ω⟩` of the subgroup of order `n`, divided by
+/// the linear factors of a few excluded points of that coset.
+///
+/// Evaluating one costs a single exponentiation plus one multiplication per
+/// excluded point, so it never has to be expanded into coefficients over the
+/// whole domain.
+#[derive(Debug, Clone)]
+pub struct Vanishing {
+    order: usize,
+    shift: FieldElement,
+    excluded: Vec<FieldElement>,
+}
+
+impl Vanishing {
+    /// `X^n - 1`, vanishing on the subgroup of order `n`.
+    pub fn subgroup(order: usize, field: Field) -> Self {
+        Vanishing::coset(order, field.one())
+    }
+
+    /// `X^n - offset^n`, vanishing on the coset `offset·⟨ω⟩` of the subgroup
+    /// of order `n`.
+    pub fn coset(order: usize, offset: FieldElement) -> Self {
+        assert!(order > 0, "a vanishing polynomial needs a non empty domain");
+        Vanishing {
+            order,
+            shift: offset.modexp(order),
+            excluded: Vec::new(),
+        }
+    }
+
+    /// `(X^n - 1) / (X - ω^{n-1})`, vanishing on every row of the trace domain
+    /// generated by `omega` except the last one, which has no successor for a
+    /// transition constraint to talk about.
+    pub fn transition(omega: &FieldElement, order: usize) -> Self {
+        Vanishing::subgroup(order, omega.field).excluding(&[omega.modexp(order - 1)])
+    }
+
+    /// Removes `points` from the zero set. Every point has to lie on the coset
+    /// so that the division stays exact.
+    pub fn excluding(mut self, points: &[FieldElement]) -> Self {
+        for point in points {
+            assert!(
+                point.modexp(self.order) == self.shift,
+                "excluded point isn't a root of the vanishing polynomial"
+            );
+            assert!(!self.excluded.contains(point), "point is already excluded");
+            self.excluded.push(*point);
+        }
+
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn degree(&self) -> usize {
+        self.order - self.excluded.len()
+    }
+
+    pub fn evaluate(&self, point: &FieldElement) -> FieldElement {
+        let numerator = point.modexp(self.order) - self.shift;
+        let denominator = self.denominator(point);
+        if !denominator.is_zero() {
+            return numerator / denominator;
+        }
+
+        // `point` is one of the excluded roots, where the quotient takes the
+        // value `n·x^{n-1} / ∏_{e ≠ x} (x - e)`.
+        let field = point.field;
+        let others = self
+            .excluded
+            .iter()
+            .filter(|e| *e != point)
+            .fold(field.one(), |acc, e| acc * (*point - *e));
+        let order = FieldElement::from_u64(self.order as u64, field);
+
+        order * point.modexp(self.order - 1) / others
+    }
+
+    pub fn evaluate_domain(&self, domain: &[FieldElement]) -> Vec<FieldElement> {
+        let denominators: Vec<FieldElement> = domain.iter().map(|d| self.denominator(d)).collect();
+        if denominators.iter().any(|d| d.is_zero()) {
+            return domain.iter().map(|d| self.evaluate(d)).collect();
+        }
+
+        iter!(domain)
+            .zip(batch_inverse(&denominators))
+            .map(|(d, inv)| (d.modexp(self.order) - self.shift) * inv)
+            .collect()
+    }
+
+    /// Divides the codeword of a polynomial divisible by this one, pointwise.
+    /// `domain` must be disjoint from the zero set, e.g. a coset of a larger
+    /// subgroup used as the evaluation domain.
+    pub fn divide_codeword(
+        &self,
+        codeword: &[FieldElement],
+        domain: &[FieldElement],
+    ) -> Vec<FieldElement> {
+        assert!(
+            codeword.len() == domain.len(),
+            "codeword and domain not the same length"
+        );
+
+        let numerators: Vec<FieldElement> = domain
+            .iter()
+            .map(|d| d.modexp(self.order) - self.shift)
+            .collect();
+        assert!(
+            numerators.iter().all(|n| !n.is_zero()),
+            "evaluation domain intersects the zero set"
+        );
+
+        codeword
+            .iter()
+            .zip(domain)
+            .zip(batch_inverse(&numerators))
+            .map(|((c, d), inv)| *c * self.denominator(d) * inv)
+            .collect()
+    }
+
+    /// Divides `polynomial` by this one in coefficient form, in time linear in
+    /// its degree. Panics if the division isn't exact.
+    #[allow(dead_code)]
+    pub fn divide(&self, polynomial: &UPolynomial) -> UPolynomial {
+        // f / ((X^n - s) / ∏ (X - e)) = f·∏ (X - e) / (X^n - s)
+        let mut remainder: Vec<FieldElement> = polynomial.coefficients().to_vec();
+        for e in &self.excluded {
+            remainder = multiply_linear(&remainder, e);
+        }
+
+        let field = self.shift.field;
+        if remainder.len() <= self.order {
+            assert!(
+                remainder.iter().all(|c| c.is_zero()),
+                "polynomial isn't divisible by the vanishing polynomial"
+            );
+            return UPolynomial::new(Vec::new());
+        }
+
+        let mut quotient = vec![field.zero(); remainder.len() - self.order];
+        for i in (self.order..remainder.len()).rev() {
+            let coefficient = remainder[i];
+            quotient[i - self.order] = coefficient;
+            remainder[i - self.order] += self.shift * coefficient;
+        }
+        assert!(
+            remainder[..self.order].iter().all(|c| c.is_zero()),
+            "polynomial isn't divisible by the vanishing polynomial"
+        );
+
+        UPolynomial::new(quotient)
+    }
+
+    /// Expands into coefficient form.
+    pub fn to_polynomial(&self) -> UPolynomial {
+        let field = self.shift.field;
+        let mut coefficients = vec![field.zero(); self.order + 1];
+        coefficients[0] = -self.shift;
+        coefficients[self.order] = field.one();
+
+        for e in &self.excluded {
+            // Synthetic division by (X - e), exact since `e` is a root.
+            let mut quotient = vec![field.zero(); coefficients.len() - 1];
+            let mut carry = field.zero();
+            for i in (1..coefficients.len()).rev() {
+                carry = coefficients[i] + carry * *e;
+                quotient[i - 1] = carry;
+            }
+            coefficients = quotient;
+        }
+
+        UPolynomial::new(coefficients)
+    }
+
+    fn denominator(&self, point: &FieldElement) -> FieldElement {
+        self.excluded
+            .iter()
+            .fold(point.field.one(), |acc, e| acc * (*point - *e))
+    }
+}
+
+/// Multiplies the coefficients of a polynomial by `(X - root)`.
+fn multiply_linear(coefficients: &[FieldElement], root: &FieldElement) -> Vec<FieldElement> {
+    if coefficients.is_empty() {
+        return Vec::new();
+    }
+
+    let field = root.field;
+    let mut product = vec![field.zero(); coefficients.len() + 1];
+    for (i, c) in coefficients.iter().enumerate() {
+        product[i + 1] += *c;
+        product[i] += -(*root * *c);
+    }
+
+    product
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{algebra::Field, domain::Domain, testing};
+
+    const ORDER: usize = 16;
+
+    #[test]
+    fn evaluation_matches_the_expanded_polynomial() {
+        let field = Field::new();
+        let mut rng = testing::rng(27);
+        let domain = Domain::new(ORDER, field).unwrap();
+        let omega = domain.root();
+        let points = domain.elements();
+        let vanishings = [
+            Vanishing::subgroup(ORDER, field),
+            Vanishing::transition(&omega, ORDER),
+            Vanishing::subgroup(ORDER, field).excluding(&[points[0], points[3], points[9]]),
+            Vanishing::coset(1, omega.inverse()),
+        ];
+
+        for vanishing in vanishings {
+            let polynomial = vanishing.to_polynomial();
+            assert_eq!(polynomial.degree(), vanishing.degree() as i128);
+
+            // The domain holds the excluded roots as well as the real ones.
+            let outside = rng.elements(4);
+            for point in points.iter().chain(&outside) {
+                assert_eq!(vanishing.evaluate(point), polynomial.evaluate(point));
+            }
+            assert_eq!(
+                vanishing.evaluate_domain(&points),
+                polynomial.evaluate_domain(&points)
+            );
+            assert_eq!(
+                vanishing.evaluate_domain(&outside),
+                polynomial.evaluate_domain(&outside)
+            );
+        }
+    }
+
+    #[test]
+    fn division_is_exact() {
+        let field = Field::new();
+        let mut rng = testing::rng(28);
+        let omega = Domain::new(ORDER, field).unwrap().root();
+        let vanishing = Vanishing::transition(&omega, ORDER);
+        let evaluation_domain = Domain::coset(4 * ORDER, field.generator())
+            .unwrap()
+            .elements();
+
+        let quotient = rng.polynomial(2 * ORDER);
+        let dividend = quotient.clone() * vanishing.to_polynomial();
+        assert_eq!(vanishing.divide(&dividend), quotient);
+
+        let codeword = dividend.evaluate_domain(&evaluation_domain);
+        assert_eq!(
+            vanishing.divide_codeword(&codeword, &evaluation_domain),
+            quotient.evaluate_domain(&evaluation_domain)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't divisible")]
+    fn inexact_division_panics() {
+        let field = Field::new();
+        let omega = Domain::new(ORDER, field).unwrap().root();
+        let vanishing = Vanishing::transition(&omega, ORDER);
+        let dividend = vanishing.to_polynomial() + UPolynomial::new(vec![field.one()]);
+
+        vanishing.divide(&dividend);
+    }
+}
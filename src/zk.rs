@@ -0,0 +1,170 @@
+use crate::{
+    algebra::FieldElement, domain::Domain, evaluation::EvaluationForm, parallel::*,
+    random::FieldRng, unipolynomial::UPolynomial,
+};
+
+/// Interpolates every trace column over the trace domain plus the given
+/// randomizer points, column `j` taking the random values `randomizers[j]`
+/// there.
+///
+/// A column randomized with `k` values stays independent of the witness for
+/// up to `k` openings outside the trace domain, so `k` has to cover the DEEP
+/// openings at `z` and `ω·z` plus every FRI query. The randomizer points must
+/// avoid the trace domain, and should avoid the evaluation domain too.
+///
+/// The result is `f + Z·r`, where `f` interpolates the column over the trace
+/// domain with an NTT, `Z` vanishes on the trace domain, and `r` interpolates
+/// the corrections `(randomizer - f(p)) / Z(p)` over the randomizer points.
+/// Only the `k` randomizer points need a generic interpolation.
+pub fn randomize_trace(
+    columns: &[Vec<FieldElement>],
+    trace_domain: &Domain,
+    randomizer_points: &[FieldElement],
+    randomizers: &[Vec<FieldElement>],
+) -> Vec<UPolynomial> {
+    assert!(
+        columns.len() == randomizers.len()
+            && randomizers
+                .iter()
+                .all(|r| r.len() == randomizer_points.len()),
+        "every trace column needs one randomizer per randomizer point"
+    );
+    let zerofier = trace_domain.vanishing();
+    let zerofier_values = zerofier.evaluate_domain(randomizer_points);
+    assert!(
+        zerofier_values.iter().all(|z| !z.is_zero()),
+        "randomizer points have to lie outside the trace domain"
+    );
+    let zerofier = zerofier.to_polynomial();
+
+    iter!(columns)
+        .zip(randomizers)
+        .map(|(column, randomness)| {
+            let polynomial = EvaluationForm::new(*trace_domain, column.clone()).to_polynomial();
+            if randomizer_points.is_empty() {
+                return polynomial;
+            }
+            let corrections: Vec<FieldElement> = randomizer_points
+                .iter()
+                .zip(randomness)
+                .zip(&zerofier_values)
+                .map(|((p, r), z)| (*r - polynomial.evaluate(p)) / *z)
+                .collect();
+            let correction =
+                UPolynomial::new(Vec::new()).interpolate_domain(randomizer_points, &corrections);
+
+            polynomial + zerofier.clone() * correction
+        })
+        .collect()
+}
+
+/// Draws `count` randomizers for each of `num_columns` trace columns, to pass
+/// to `randomize_trace`.
+pub fn sample_randomizers(
+    rng: &mut FieldRng,
+    num_columns: usize,
+    count: usize,
+) -> Vec<Vec<FieldElement>> {
+    (0..num_columns).map(|_| rng.elements(count)).collect()
+}
+
+/// Random low degree polynomial added to the DEEP composition before it goes
+/// into FRI, so that the FRI layers and their openings are masked as well.
+/// Its codeword gets committed alongside the trace and opened at every query.
+/// The codeword is only evaluated on request, so that a streaming prover can
+/// extend it a part of the domain at a time.
+#[derive(Debug, Clone)]
+pub struct CompositionMask {
+    polynomial: UPolynomial,
+}
+
+impl CompositionMask {
+    /// `coefficients` should be uniformly random, and as many as the degree
+    /// bound FRI tests the composition against.
+    pub fn new(coefficients: Vec<FieldElement>) -> Self {
+        CompositionMask {
+            polynomial: UPolynomial::new(coefficients),
+        }
+    }
+
+    /// Mask with `degree_bound` uniformly random coefficients.
+    pub fn sample(rng: &mut FieldRng, degree_bound: usize) -> Self {
+        Self::new(rng.elements(degree_bound))
+    }
+
+    pub fn polynomial(&self) -> &UPolynomial {
+        &self.polynomial
+    }
+
+    #[allow(dead_code)]
+    pub fn codeword(&self, domain: &Domain) -> Vec<FieldElement> {
+        EvaluationForm::from_polynomial(&self.polynomial, *domain)
+            .values()
+            .to_vec()
+    }
+
+    #[allow(dead_code)]
+    pub fn apply(&self, composition: &[FieldElement], domain: &Domain) -> Vec<FieldElement> {
+        assert!(
+            composition.len() == domain.size(),
+            "composition and domain not the same length"
+        );
+        composition
+            .iter()
+            .zip(self.codeword(domain))
+            .map(|(c, m)| *c + m)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn randomized_trace_matches_randomized_interpolation() {
+        let mut rng = testing::rng(50);
+        let trace_domain = Domain::new(16, rng.field()).unwrap();
+        let columns = vec![rng.elements(16), rng.elements(16)];
+        let points = rng.distinct_elements(5);
+        let randomizers = sample_randomizers(&mut rng, 2, 5);
+
+        let polynomials = randomize_trace(&columns, &trace_domain, &points, &randomizers);
+        for ((polynomial, column), randomness) in polynomials.iter().zip(&columns).zip(&randomizers)
+        {
+            let expected = UPolynomial::new(Vec::new()).interpolate_randomized(
+                &trace_domain.elements(),
+                column,
+                &points,
+                randomness,
+            );
+            assert_eq!(polynomial, &expected);
+            assert_eq!(polynomial.degree(), 20);
+        }
+
+        // Without randomizers it's the plain interpolant.
+        let plain = randomize_trace(&columns, &trace_domain, &[], &[vec![], vec![]]);
+        for (polynomial, column) in plain.iter().zip(&columns) {
+            assert_eq!(
+                &polynomial.evaluate_domain(&trace_domain.elements()),
+                column
+            );
+            assert!(polynomial.degree() < 16);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "randomizer points have to lie outside the trace domain")]
+    fn randomizer_points_on_the_trace_domain_are_rejected() {
+        let mut rng = testing::rng(51);
+        let trace_domain = Domain::new(8, rng.field()).unwrap();
+        let points = [rng.element(), trace_domain.element(3)];
+        randomize_trace(
+            &[rng.elements(8)],
+            &trace_domain,
+            &points,
+            &sample_randomizers(&mut rng, 1, 2),
+        );
+    }
+}